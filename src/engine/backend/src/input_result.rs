@@ -6,6 +6,11 @@ bitflags::bitflags! {
         const HAS_PREEDIT = 0b100;
         const HAS_COMMIT = 0b1000;
         const NOT_READY = 0b10000;
+        const HAS_CANDIDATE = 0b100000;
+        /// Key is held for sequence or chord hotkey, frontend should keep it until next result
+        const HOLD = 0b1000000;
+        /// Frontend should process held keys again then current key if there is
+        const REPLAY = 0b10000000;
     }
 }
 
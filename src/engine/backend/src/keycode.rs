@@ -14,10 +14,49 @@ bitflags::bitflags! {
         const CONTROL = 0x2;
         const SUPER = 0x4;
         const ALT = 0x8;
+        /// AltGr (ISO_Level3_Shift)
+        const ALTGR = 0x10;
+        const CAPSLOCK = 0x20;
+        const NUMLOCK = 0x40;
+    }
+}
+
+impl ModifierState {
+    /// Lock states which are not pressed with key
+    pub const fn locks() -> Self {
+        Self::from_bits_truncate(Self::CAPSLOCK.bits() | Self::NUMLOCK.bits())
+    }
+}
+
+/// How CapsLock changes key for layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapsLockBehavior {
+    /// CapsLock does nothing
+    Ignore,
+    /// CapsLock inverts shift of letter keys
+    Letters,
+    /// CapsLock inverts shift of every key
+    Shift,
+}
+
+impl CapsLockBehavior {
+    /// Apply CapsLock to key which doesn't have lock states
+    pub fn apply(self, key: Key, capslock: bool) -> Key {
+        let invert = capslock
+            && match self {
+                Self::Ignore => false,
+                Self::Letters => key.code.is_letter(),
+                Self::Shift => true,
+            };
+
+        if invert {
+            Key::new(key.code, key.state ^ ModifierState::SHIFT)
+        } else {
+            key
+        }
     }
 }
 
-// TODO: complete
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumString, Display, Enum)]
 pub enum KeyCode {
@@ -62,6 +101,20 @@ pub enum KeyCode {
     NumNine,
     #[strum(to_string = "N0")]
     NumZero,
+    #[strum(to_string = "NPlus")]
+    NumPlus,
+    #[strum(to_string = "NMinus")]
+    NumMinus,
+    #[strum(to_string = "NMultiply")]
+    NumMultiply,
+    #[strum(to_string = "NDivide")]
+    NumDivide,
+    #[strum(to_string = "NPeriod")]
+    NumPeriod,
+    #[strum(to_string = "NEqual")]
+    NumEqual,
+    #[strum(to_string = "NEnter")]
+    NumEnter,
 
     Minus,
     Equal,
@@ -77,6 +130,13 @@ pub enum KeyCode {
     Quote,
     Slash,
 
+    /// 102nd key of ISO keyboard between left shift and Z
+    IntlBackslash,
+    /// JIS Yen key
+    IntlYen,
+    /// JIS Ro key
+    IntlRo,
+
     Q,
     W,
     E,
@@ -107,7 +167,9 @@ pub enum KeyCode {
     M,
 
     Esc,
-    Shift,
+    #[strum(to_string = "ShiftL", serialize = "Shift")]
+    ShiftL,
+    ShiftR,
     Backspace,
     Enter,
     Tab,
@@ -123,8 +185,19 @@ pub enum KeyCode {
     Henkan,
     AltL,
     AltR,
+    SuperL,
+    SuperR,
+    Menu,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    Pause,
+    PrintScreen,
     Hangul,
     HangulHanja,
+    Katakana,
+    Hiragana,
+    KatakanaHiragana,
 
     Left,
     Right,
@@ -143,9 +216,73 @@ pub enum KeyCode {
     F10,
     F11,
     F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    MediaPlayPause,
+    MediaStop,
+    MediaPrevious,
+    MediaNext,
 }
 
 impl KeyCode {
+    pub const fn is_letter(self) -> bool {
+        matches!(
+            self,
+            Self::A
+                | Self::B
+                | Self::C
+                | Self::D
+                | Self::E
+                | Self::F
+                | Self::G
+                | Self::H
+                | Self::I
+                | Self::J
+                | Self::K
+                | Self::L
+                | Self::M
+                | Self::N
+                | Self::O
+                | Self::P
+                | Self::Q
+                | Self::R
+                | Self::S
+                | Self::T
+                | Self::U
+                | Self::V
+                | Self::W
+                | Self::X
+                | Self::Y
+                | Self::Z
+        )
+    }
+
+    /// Modifier which is set while this key is pressed
+    pub const fn modifier(self) -> ModifierState {
+        match self {
+            Self::ShiftL | Self::ShiftR => ModifierState::SHIFT,
+            Self::ControlL | Self::ControlR => ModifierState::CONTROL,
+            Self::SuperL | Self::SuperR => ModifierState::SUPER,
+            // AltR can be AltGr
+            Self::AltL => ModifierState::ALT,
+            Self::AltR => ModifierState::from_bits_truncate(
+                ModifierState::ALT.bits() | ModifierState::ALTGR.bits(),
+            ),
+            _ => ModifierState::empty(),
+        }
+    }
+
     pub const fn from_hardware_code(code: u16, numlock: bool) -> Option<Self> {
         match code {
             10 => Some(Self::One),
@@ -168,12 +305,33 @@ impl KeyCode {
             81 if numlock => Some(Self::NumNine),
             19 => Some(Self::Zero),
             90 if numlock => Some(Self::NumZero),
+            91 if numlock => Some(Self::NumPeriod),
+
+            // Keypad without numlock
+            87 => Some(Self::End),
+            88 => Some(Self::Down),
+            89 => Some(Self::PageDown),
+            83 => Some(Self::Left),
+            85 => Some(Self::Right),
+            79 => Some(Self::Home),
+            80 => Some(Self::Up),
+            81 => Some(Self::PageUp),
+            90 => Some(Self::Insert),
+            91 => Some(Self::Delete),
+
+            86 => Some(Self::NumPlus),
+            82 => Some(Self::NumMinus),
+            63 => Some(Self::NumMultiply),
+            106 => Some(Self::NumDivide),
+            125 => Some(Self::NumEqual),
+            104 => Some(Self::NumEnter),
+
             20 => Some(Self::Minus),
             21 => Some(Self::Equal),
             34 => Some(Self::OpenBracket),
             35 => Some(Self::CloseBracket),
-            // Shift_L, Shift_R
-            50 | 62 => Some(Self::Shift),
+            50 => Some(Self::ShiftL),
+            62 => Some(Self::ShiftR),
             51 => Some(Self::Backslash),
             61 => Some(Self::Slash),
             47 => Some(Self::SemiColon),
@@ -182,6 +340,10 @@ impl KeyCode {
             59 => Some(Self::Comma),
             60 => Some(Self::Period),
 
+            94 => Some(Self::IntlBackslash),
+            132 => Some(Self::IntlYen),
+            97 => Some(Self::IntlRo),
+
             24 => Some(Self::Q),
             25 => Some(Self::W),
             26 => Some(Self::E),
@@ -229,8 +391,20 @@ impl KeyCode {
             102 => Some(Self::Muhenkan),
             64 => Some(Self::AltL),
             108 => Some(Self::AltR),
+            133 => Some(Self::SuperL),
+            134 => Some(Self::SuperR),
+            135 => Some(Self::Menu),
+            66 => Some(Self::CapsLock),
+            77 => Some(Self::NumLock),
+            78 => Some(Self::ScrollLock),
+            127 => Some(Self::Pause),
+            107 => Some(Self::PrintScreen),
+            // 121 ~ 123 are volume keys in evdev but used by some korean keyboards
             122 | 130 => Some(Self::Hangul),
             121 | 123 | 131 => Some(Self::HangulHanja),
+            98 => Some(Self::Katakana),
+            99 => Some(Self::Hiragana),
+            101 => Some(Self::KatakanaHiragana),
 
             113 => Some(Self::Left),
             114 => Some(Self::Right),
@@ -250,6 +424,186 @@ impl KeyCode {
 
             95 => Some(Self::F11),
             96 => Some(Self::F12),
+            191 => Some(Self::F13),
+            192 => Some(Self::F14),
+            193 => Some(Self::F15),
+            194 => Some(Self::F16),
+            195 => Some(Self::F17),
+            196 => Some(Self::F18),
+            197 => Some(Self::F19),
+            198 => Some(Self::F20),
+            199 => Some(Self::F21),
+            200 => Some(Self::F22),
+            201 => Some(Self::F23),
+            202 => Some(Self::F24),
+
+            172 => Some(Self::MediaPlayPause),
+            174 => Some(Self::MediaStop),
+            173 => Some(Self::MediaPrevious),
+            171 => Some(Self::MediaNext),
+
+            _ => None,
+        }
+    }
+
+    /// Map X keysym into key of same symbol in US qwerty
+    ///
+    /// Shifted symbols (e.g. `!`) are mapped to their unshifted key (e.g. `1`)
+    pub const fn from_keysym(keysym: u32) -> Option<Self> {
+        match keysym {
+            0x31 | 0x21 => Some(Self::One),
+            0x32 | 0x40 => Some(Self::Two),
+            0x33 | 0x23 => Some(Self::Three),
+            0x34 | 0x24 => Some(Self::Four),
+            0x35 | 0x25 => Some(Self::Five),
+            0x36 | 0x5E => Some(Self::Six),
+            0x37 | 0x26 => Some(Self::Seven),
+            0x38 | 0x2A => Some(Self::Eight),
+            0x39 | 0x28 => Some(Self::Nine),
+            0x30 | 0x29 => Some(Self::Zero),
+
+            // KP_0 ~ KP_9 only come when numlock is on
+            0xFFB1 => Some(Self::NumOne),
+            0xFFB2 => Some(Self::NumTwo),
+            0xFFB3 => Some(Self::NumThree),
+            0xFFB4 => Some(Self::NumFour),
+            0xFFB5 => Some(Self::NumFive),
+            0xFFB6 => Some(Self::NumSix),
+            0xFFB7 => Some(Self::NumSeven),
+            0xFFB8 => Some(Self::NumEight),
+            0xFFB9 => Some(Self::NumNine),
+            0xFFB0 => Some(Self::NumZero),
+            0xFFAE => Some(Self::NumPeriod),
+            0xFFAB => Some(Self::NumPlus),
+            0xFFAD => Some(Self::NumMinus),
+            0xFFAA => Some(Self::NumMultiply),
+            0xFFAF => Some(Self::NumDivide),
+            0xFFBD => Some(Self::NumEqual),
+            0xFF8D => Some(Self::NumEnter),
+
+            // Keypad without numlock
+            0xFF9C => Some(Self::End),
+            0xFF99 => Some(Self::Down),
+            0xFF9B => Some(Self::PageDown),
+            0xFF96 => Some(Self::Left),
+            0xFF98 => Some(Self::Right),
+            0xFF95 => Some(Self::Home),
+            0xFF97 => Some(Self::Up),
+            0xFF9A => Some(Self::PageUp),
+            0xFF9E => Some(Self::Insert),
+            0xFF9F => Some(Self::Delete),
+
+            0x2D | 0x5F => Some(Self::Minus),
+            0x3D | 0x2B => Some(Self::Equal),
+            0x5C | 0x7C => Some(Self::Backslash),
+            0x60 | 0x7E => Some(Self::Grave),
+            0x5B | 0x7B => Some(Self::OpenBracket),
+            0x5D | 0x7D => Some(Self::CloseBracket),
+            0x20 => Some(Self::Space),
+
+            0x2C | 0x3C => Some(Self::Comma),
+            0x2E | 0x3E => Some(Self::Period),
+            0x3B | 0x3A => Some(Self::SemiColon),
+            0x27 | 0x22 => Some(Self::Quote),
+            0x2F | 0x3F => Some(Self::Slash),
+
+            0x71 | 0x51 => Some(Self::Q),
+            0x77 | 0x57 => Some(Self::W),
+            0x65 | 0x45 => Some(Self::E),
+            0x72 | 0x52 => Some(Self::R),
+            0x74 | 0x54 => Some(Self::T),
+            0x79 | 0x59 => Some(Self::Y),
+            0x75 | 0x55 => Some(Self::U),
+            0x69 | 0x49 => Some(Self::I),
+            0x6F | 0x4F => Some(Self::O),
+            0x70 | 0x50 => Some(Self::P),
+
+            0x61 | 0x41 => Some(Self::A),
+            0x73 | 0x53 => Some(Self::S),
+            0x64 | 0x44 => Some(Self::D),
+            0x66 | 0x46 => Some(Self::F),
+            0x67 | 0x47 => Some(Self::G),
+            0x68 | 0x48 => Some(Self::H),
+            0x6A | 0x4A => Some(Self::J),
+            0x6B | 0x4B => Some(Self::K),
+            0x6C | 0x4C => Some(Self::L),
+
+            0x7A | 0x5A => Some(Self::Z),
+            0x78 | 0x58 => Some(Self::X),
+            0x63 | 0x43 => Some(Self::C),
+            0x76 | 0x56 => Some(Self::V),
+            0x62 | 0x42 => Some(Self::B),
+            0x6E | 0x4E => Some(Self::N),
+            0x6D | 0x4D => Some(Self::M),
+
+            0xFF1B => Some(Self::Esc),
+            0xFFE1 => Some(Self::ShiftL),
+            0xFFE2 => Some(Self::ShiftR),
+            0xFF08 => Some(Self::Backspace),
+            0xFF0D => Some(Self::Enter),
+            // Tab, ISO_Left_Tab
+            0xFF09 | 0xFE20 => Some(Self::Tab),
+            0xFFE3 => Some(Self::ControlL),
+            0xFFE4 => Some(Self::ControlR),
+            0xFFFF => Some(Self::Delete),
+            0xFF63 => Some(Self::Insert),
+            0xFF50 => Some(Self::Home),
+            0xFF57 => Some(Self::End),
+            0xFF55 => Some(Self::PageUp),
+            0xFF56 => Some(Self::PageDown),
+            0xFF22 => Some(Self::Muhenkan),
+            0xFF23 => Some(Self::Henkan),
+            0xFFE9 => Some(Self::AltL),
+            // Alt_R, ISO_Level3_Shift
+            0xFFEA | 0xFE03 => Some(Self::AltR),
+            0xFFEB => Some(Self::SuperL),
+            0xFFEC => Some(Self::SuperR),
+            0xFF67 => Some(Self::Menu),
+            0xFFE5 => Some(Self::CapsLock),
+            0xFF7F => Some(Self::NumLock),
+            0xFF14 => Some(Self::ScrollLock),
+            0xFF13 => Some(Self::Pause),
+            0xFF61 => Some(Self::PrintScreen),
+            0xFF31 => Some(Self::Hangul),
+            0xFF34 => Some(Self::HangulHanja),
+            0xFF26 => Some(Self::Katakana),
+            0xFF25 => Some(Self::Hiragana),
+            0xFF27 => Some(Self::KatakanaHiragana),
+
+            0xFF51 => Some(Self::Left),
+            0xFF53 => Some(Self::Right),
+            0xFF52 => Some(Self::Up),
+            0xFF54 => Some(Self::Down),
+
+            0xFFBE => Some(Self::F1),
+            0xFFBF => Some(Self::F2),
+            0xFFC0 => Some(Self::F3),
+            0xFFC1 => Some(Self::F4),
+            0xFFC2 => Some(Self::F5),
+            0xFFC3 => Some(Self::F6),
+            0xFFC4 => Some(Self::F7),
+            0xFFC5 => Some(Self::F8),
+            0xFFC6 => Some(Self::F9),
+            0xFFC7 => Some(Self::F10),
+            0xFFC8 => Some(Self::F11),
+            0xFFC9 => Some(Self::F12),
+            0xFFCA => Some(Self::F13),
+            0xFFCB => Some(Self::F14),
+            0xFFCC => Some(Self::F15),
+            0xFFCD => Some(Self::F16),
+            0xFFCE => Some(Self::F17),
+            0xFFCF => Some(Self::F18),
+            0xFFD0 => Some(Self::F19),
+            0xFFD1 => Some(Self::F20),
+            0xFFD2 => Some(Self::F21),
+            0xFFD3 => Some(Self::F22),
+            0xFFD4 => Some(Self::F23),
+            0xFFD5 => Some(Self::F24),
+
+            0x1008FF14 => Some(Self::MediaPlayPause),
+            0x1008FF15 => Some(Self::MediaStop),
+            0x1008FF16 => Some(Self::MediaPrevious),
+            0x1008FF17 => Some(Self::MediaNext),
 
             _ => None,
         }
@@ -298,6 +652,10 @@ impl fmt::Display for Key {
             f.write_str("M-")?;
         }
 
+        if self.state.contains(ModifierState::ALTGR) {
+            f.write_str("AG-")?;
+        }
+
         if self.state.contains(ModifierState::CONTROL) {
             f.write_str("C-")?;
         }
@@ -329,6 +687,12 @@ impl FromStr for Key {
                 continue;
             }
 
+            if let Some(n) = s.strip_prefix("AG-") {
+                s = n;
+                state |= ModifierState::ALTGR;
+                continue;
+            }
+
             if let Some(n) = s.strip_prefix("C-") {
                 s = n;
                 state |= ModifierState::CONTROL;
@@ -378,4 +742,50 @@ fn key_parse() {
     assert_eq!("S-4".parse::<Key>().unwrap(), Key::shift(KeyCode::Four));
     assert_eq!("C-Space".parse::<Key>().unwrap(), Key::ctrl(KeyCode::Space));
     assert_eq!("M-X".parse::<Key>().unwrap(), Key::alt(KeyCode::X));
+    assert_eq!(
+        "NEnter".parse::<Key>().unwrap(),
+        Key::normal(KeyCode::NumEnter)
+    );
+    assert_eq!("S-F13".parse::<Key>().unwrap(), Key::shift(KeyCode::F13));
+    assert_eq!(
+        "Shift".parse::<Key>().unwrap(),
+        Key::normal(KeyCode::ShiftL)
+    );
+    assert_eq!(KeyCode::ShiftL.to_string(), "ShiftL");
+    assert_eq!(
+        "AG-S-E".parse::<Key>().unwrap(),
+        Key::new(KeyCode::E, ModifierState::ALTGR | ModifierState::SHIFT)
+    );
+}
+
+#[test]
+fn capslock() {
+    let a = Key::normal(KeyCode::A);
+    let one = Key::normal(KeyCode::One);
+
+    assert_eq!(CapsLockBehavior::Ignore.apply(a, true), a);
+    assert_eq!(
+        CapsLockBehavior::Letters.apply(a, true),
+        Key::shift(KeyCode::A)
+    );
+    assert_eq!(
+        CapsLockBehavior::Letters.apply(Key::shift(KeyCode::A), true),
+        a
+    );
+    assert_eq!(CapsLockBehavior::Letters.apply(one, true), one);
+    assert_eq!(
+        CapsLockBehavior::Shift.apply(one, true),
+        Key::shift(KeyCode::One)
+    );
+    assert_eq!(CapsLockBehavior::Shift.apply(one, false), one);
+}
+
+#[test]
+fn keysym() {
+    assert_eq!(KeyCode::from_keysym(0x61), Some(KeyCode::A));
+    assert_eq!(KeyCode::from_keysym(0x41), Some(KeyCode::A));
+    assert_eq!(KeyCode::from_keysym(0x21), Some(KeyCode::One));
+    assert_eq!(KeyCode::from_keysym(0xFF31), Some(KeyCode::Hangul));
+    assert_eq!(KeyCode::from_keysym(0xFF9C), Some(KeyCode::End));
+    assert_eq!(KeyCode::from_keysym(0xFFFFFF), None);
 }
@@ -5,6 +5,7 @@ use serde::{
     Deserialize,
 };
 use std::{
+    collections::BTreeMap,
     fmt,
     iter::{FromIterator, IntoIterator},
     marker::PhantomData,
@@ -12,38 +13,55 @@ use std::{
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct KeyMap<V> {
+    /// Keys without modifier or with only shift
     arr: EnumMap<KeyCode, [Option<V>; 2]>,
+    /// Keys with other modifiers e.g. AltGr, Control
+    layers: BTreeMap<Key, V>,
 }
 
-impl<V: Copy> Default for KeyMap<V> {
+impl<V> Default for KeyMap<V> {
     fn default() -> Self {
         Self::new()
     }
 }
 
 impl<V: Copy> KeyMap<V> {
+    pub fn get(&self, key: Key) -> Option<V> {
+        self.get_ref(key).copied()
+    }
+}
+
+impl<V> KeyMap<V> {
     pub fn new() -> Self {
         Self {
             arr: EnumMap::default(),
+            layers: BTreeMap::new(),
         }
     }
 
-    pub fn get(&self, key: Key) -> Option<V> {
+    pub fn get_ref(&self, key: Key) -> Option<&V> {
         if key.state.intersects(!ModifierState::SHIFT) {
-            None
+            self.layers.get(&key)
         } else {
             // SAFETY: key.state <= 0x1
-            unsafe { *self.arr[key.code].get_unchecked(key.state.bits() as usize) }
+            unsafe {
+                self.arr[key.code]
+                    .get_unchecked(key.state.bits() as usize)
+                    .as_ref()
+            }
         }
     }
 
-    /// Key must don't have shift modifier
     pub fn insert(&mut self, key: Key, value: V) {
-        self.arr[key.code][key.state.bits() as usize] = Some(value);
+        if key.state.intersects(!ModifierState::SHIFT) {
+            self.layers.insert(key, value);
+        } else {
+            self.arr[key.code][key.state.bits() as usize] = Some(value);
+        }
     }
 }
 
-impl<V: Copy> FromIterator<(Key, V)> for KeyMap<V> {
+impl<V> FromIterator<(Key, V)> for KeyMap<V> {
     fn from_iter<T: IntoIterator<Item = (Key, V)>>(iter: T) -> Self {
         let mut map = Self::new();
         for item in iter {
@@ -55,7 +73,7 @@ impl<V: Copy> FromIterator<(Key, V)> for KeyMap<V> {
 
 struct KeyMapVisitor<V>(PhantomData<V>);
 
-impl<'de, V: Copy> Visitor<'de> for KeyMapVisitor<V>
+impl<'de, V> Visitor<'de> for KeyMapVisitor<V>
 where
     V: Deserialize<'de>,
 {
@@ -79,7 +97,7 @@ where
     }
 }
 
-impl<'de, V: Copy> Deserialize<'de> for KeyMap<V>
+impl<'de, V> Deserialize<'de> for KeyMap<V>
 where
     V: Deserialize<'de>,
 {
@@ -93,7 +111,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Key, KeyCode, KeyMap};
+    use super::{Key, KeyCode, KeyMap, ModifierState};
 
     #[test]
     fn insert() {
@@ -101,4 +119,18 @@ mod tests {
         map.insert(Key::normal(KeyCode::Backspace), 123);
         assert_eq!(map.get(Key::normal(KeyCode::Backspace)), Some(123));
     }
+
+    #[test]
+    fn layers() {
+        let mut map = KeyMap::new();
+        map.insert(Key::new(KeyCode::E, ModifierState::ALTGR), '€');
+        map.insert(Key::ctrl(KeyCode::H), '\u{8}');
+        assert_eq!(
+            map.get(Key::new(KeyCode::E, ModifierState::ALTGR)),
+            Some('€')
+        );
+        assert_eq!(map.get(Key::ctrl(KeyCode::H)), Some('\u{8}'));
+        assert_eq!(map.get(Key::normal(KeyCode::E)), None);
+        assert_eq!(map.get(Key::alt(KeyCode::H)), None);
+    }
 }
@@ -2,7 +2,7 @@ mod input_result;
 mod keycode;
 mod keymap;
 
-pub use keycode::{Key, KeyCode, ModifierState};
+pub use keycode::{CapsLockBehavior, Key, KeyCode, ModifierState};
 pub use keymap::KeyMap;
 
 pub use input_result::InputResult;
@@ -1,13 +1,15 @@
 mod characters;
+mod ksx1001;
 mod layout;
 mod state;
 
 use std::{borrow::Cow, collections::BTreeMap};
 
 use enumset::{EnumSet, EnumSetType};
-use kime_engine_backend::{InputEngineBackend, Key, KeyCode};
+use kime_engine_backend::{CapsLockBehavior, InputEngineBackend, Key, KeyCode};
 use serde::{Deserialize, Serialize};
 
+pub use ksx1001::{is_ksx1001, is_ksx1001_locale, ksx1001_syllable_code};
 pub use layout::Layout;
 pub use state::HangulEngine;
 
@@ -51,17 +53,48 @@ impl Default for PreeditJohabLevel {
 #[serde(default)]
 pub struct HangulConfig {
     pub layout: String,
+    /// Other layouts which can be switched by `CycleLayout` after `layout`
+    pub layouts: Vec<String>,
     pub word_commit: bool,
     pub preedit_johab: PreeditJohabLevel,
+    /// Only commit 2350 KS X 1001 syllables
+    pub ksx1001_only: bool,
+    pub capslock_behavior: CapsLockBehavior,
     pub addons: BTreeMap<String, EnumSet<Addon>>,
 }
 
+impl HangulConfig {
+    /// Names of layouts in switching order, `layout` is always first
+    pub fn layout_names(&self) -> Vec<&str> {
+        let mut names = vec![self.layout.as_str()];
+
+        for name in self.layouts.iter() {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+
+        names
+    }
+
+    fn addons(&self, layout: &str) -> EnumSet<Addon> {
+        self.addons
+            .get("all")
+            .copied()
+            .unwrap_or_default()
+            .union(self.addons.get(layout).copied().unwrap_or_default())
+    }
+}
+
 impl Default for HangulConfig {
     fn default() -> Self {
         Self {
             layout: "dubeolsik".into(),
+            layouts: Vec::new(),
             word_commit: false,
             preedit_johab: PreeditJohabLevel::default(),
+            ksx1001_only: false,
+            capslock_behavior: CapsLockBehavior::Ignore,
             addons: vec![
                 ("all".into(), Addon::ComposeChoseongSsang.into()),
                 ("dubeolsik".into(), Addon::TreatJongseongAsChoseong.into()),
@@ -92,11 +125,19 @@ pub const BUILTIN_LAYOUTS: &'static [(&'static str, &'static str)] = &[
     ),
 ];
 
-pub struct HangulData {
+struct LayoutData {
+    name: String,
     layout: Layout,
     addons: EnumSet<Addon>,
+}
+
+pub struct HangulData {
+    /// Layouts which can be switched by `CycleLayout`, never empty
+    layouts: Vec<LayoutData>,
     preedit_johab: PreeditJohabLevel,
     word_commit: bool,
+    ksx1001_only: bool,
+    capslock_behavior: CapsLockBehavior,
 }
 
 impl Default for HangulData {
@@ -124,30 +165,64 @@ impl HangulData {
 
     pub fn new(
         config: &HangulConfig,
-        mut layouts: impl Iterator<Item = (Cow<'static, str>, Layout)>,
+        layouts: impl Iterator<Item = (Cow<'static, str>, Layout)>,
     ) -> Self {
-        Self {
-            layout: layouts
-                .find_map(|(name, layout)| {
-                    if name == config.layout {
-                        Some(layout)
-                    } else {
-                        None
-                    }
+        let names = config.layout_names();
+        let mut found = BTreeMap::new();
+
+        // Layouts which come first have priority
+        for (name, layout) in layouts {
+            if names.contains(&name.as_ref()) {
+                found.entry(name).or_insert(layout);
+            }
+        }
+
+        let mut layouts: Vec<_> = names
+            .into_iter()
+            .filter_map(|name| {
+                Some(LayoutData {
+                    layout: found.remove(name)?,
+                    addons: config.addons(name),
+                    name: name.into(),
                 })
-                .unwrap_or_default(),
-            addons: config.addons.get("all").copied().unwrap_or_default().union(
-                config
-                    .addons
-                    .get(&config.layout)
-                    .copied()
-                    .unwrap_or_default(),
-            ),
+            })
+            .collect();
+
+        if layouts.is_empty() {
+            layouts.push(LayoutData {
+                name: config.layout.clone(),
+                layout: Layout::default(),
+                addons: config.addons(&config.layout),
+            });
+        }
+
+        Self {
+            layouts,
             preedit_johab: config.preedit_johab,
             word_commit: config.word_commit,
+            ksx1001_only: config.ksx1001_only,
+            capslock_behavior: config.capslock_behavior,
         }
     }
 
+    pub fn layout_count(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Names of loaded layouts, layouts which can't be found are skipped
+    pub fn layout_names(&self) -> impl Iterator<Item = &str> {
+        self.layouts.iter().map(|l| l.name.as_str())
+    }
+
+    pub fn layout_name(&self, index: usize) -> &str {
+        &self.layout(index).name
+    }
+
+    fn layout(&self, index: usize) -> &LayoutData {
+        // Index could be from another data e.g. app profile
+        &self.layouts[index % self.layouts.len()]
+    }
+
     pub const fn preedit_johab(&self) -> PreeditJohabLevel {
         self.preedit_johab
     }
@@ -155,16 +230,27 @@ impl HangulData {
     pub const fn word_commit(&self) -> bool {
         self.word_commit
     }
+
+    pub const fn ksx1001_only(&self) -> bool {
+        self.ksx1001_only
+    }
+
+    pub const fn capslock_behavior(&self) -> CapsLockBehavior {
+        self.capslock_behavior
+    }
 }
 
 impl InputEngineBackend for HangulEngine {
     type ConfigData = HangulData;
 
     fn press_key(&mut self, config: &HangulData, key: Key, commit_buf: &mut String) -> bool {
+        let layout = config.layout(self.layout_index());
+        let addons = self.addons(layout.addons);
+
         if key.code == KeyCode::Backspace {
-            self.backspace(config.addons, commit_buf)
-        } else if let Some(kv) = config.layout.lookup_kv(key) {
-            self.key(kv, config.addons, commit_buf)
+            self.backspace(addons, commit_buf)
+        } else if let Some(kv) = layout.layout.lookup_kv(key) {
+            self.key(kv, addons, commit_buf)
         } else {
             false
         }
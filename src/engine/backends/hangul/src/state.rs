@@ -2,6 +2,7 @@ use enumset::EnumSet;
 
 use crate::{
     characters::{Choseong, JongToCho, Jongseong, Jungseong, KeyValue},
+    ksx1001::is_ksx1001,
     Addon, PreeditJohabLevel,
 };
 
@@ -11,32 +12,70 @@ pub struct HangulEngine {
     state: CharacterState,
     word_commit: bool,
     preedit_johab: PreeditJohabLevel,
+    ksx1001_only: bool,
     word_buf: String,
+    /// Index of current layout in `HangulData`
+    layout: usize,
+    /// Addons toggled by hotkey
+    toggled_addons: EnumSet<Addon>,
 }
 
 impl HangulEngine {
-    pub fn new(word_commit: bool, preedit_johab: PreeditJohabLevel) -> Self {
+    pub fn new(word_commit: bool, preedit_johab: PreeditJohabLevel, ksx1001_only: bool) -> Self {
         Self {
             state: CharacterState::new(),
             word_commit,
             preedit_johab,
+            ksx1001_only,
             word_buf: String::new(),
+            layout: 0,
+            toggled_addons: EnumSet::empty(),
         }
     }
 
+    pub const fn layout_index(&self) -> usize {
+        self.layout
+    }
+
+    /// Switch to next layout, preedit is committed since it can't be composed with other layout
+    pub fn cycle_layout(&mut self, layout_count: usize, commit_buf: &mut String) -> bool {
+        if layout_count <= 1 {
+            return false;
+        }
+
+        self.clear_preedit(commit_buf);
+        self.layout = (self.layout + 1) % layout_count;
+        true
+    }
+
+    pub fn toggle_addon(&mut self, addon: Addon) {
+        self.toggled_addons ^= addon;
+    }
+
+    /// Apply toggled addons to addons of layout
+    pub fn addons(&self, addons: EnumSet<Addon>) -> EnumSet<Addon> {
+        addons ^ self.toggled_addons
+    }
+
     pub fn has_preedit(&self) -> bool {
         self.state.need_display() || !self.word_buf.is_empty()
     }
 
     pub fn preedit_str(&self, buf: &mut String) {
         buf.push_str(&self.word_buf);
-        self.state.preedit(self.preedit_johab, buf);
+        self.state
+            .preedit(self.preedit_johab, self.ksx1001_only, buf);
+    }
+
+    /// Byte offset of preedit string where current composing character starts
+    pub fn composing_start(&self) -> usize {
+        self.word_buf.len()
     }
 
     pub fn clear_preedit(&mut self, commit_buf: &mut String) {
         commit_buf.push_str(&self.word_buf);
         self.word_buf.clear();
-        self.state.commit(commit_buf);
+        self.state.commit(self.ksx1001_only, commit_buf);
         self.state.reset();
     }
 
@@ -50,9 +89,9 @@ impl HangulEngine {
             CharacterResult::Consume => true,
             CharacterResult::NewCharacter(new_ch) => {
                 if self.word_commit {
-                    self.state.commit(&mut self.word_buf);
+                    self.state.commit(self.ksx1001_only, &mut self.word_buf);
                 } else {
-                    self.state.commit(commit_buf);
+                    self.state.commit(self.ksx1001_only, commit_buf);
                 }
                 self.state = new_ch;
                 true
@@ -131,7 +170,27 @@ impl CharacterState {
         self.jong = None;
     }
 
-    pub fn preedit(&self, preedit_johab: PreeditJohabLevel, out: &mut String) {
+    /// Push composed syllable or compatibility jamos when `ksx1001_only` is set and
+    /// syllable isn't in KS X 1001
+    fn push_syllable(
+        cho: Choseong,
+        jung: Jungseong,
+        jong: Option<Jongseong>,
+        ksx1001_only: bool,
+        out: &mut String,
+    ) {
+        let ch = cho.compose(jung, jong);
+
+        if ksx1001_only && !is_ksx1001(ch) {
+            out.push(cho.jamo());
+            out.push(jung.jamo());
+            out.extend(jong.map(Jongseong::jamo));
+        } else {
+            out.push(ch);
+        }
+    }
+
+    pub fn preedit(&self, preedit_johab: PreeditJohabLevel, ksx1001_only: bool, out: &mut String) {
         macro_rules! to_char {
             ($jamo:expr) => {
                 match preedit_johab {
@@ -170,7 +229,7 @@ impl CharacterState {
                 out.push(jong.into());
             }
             (Some(cho), Some(jung), jong, PreeditJohabLevel::Needed | PreeditJohabLevel::Never) => {
-                out.push(cho.compose(jung, jong))
+                Self::push_syllable(cho, jung, jong, ksx1001_only, out)
             }
             (Some(cho), Some(jung), jong, PreeditJohabLevel::Always) => {
                 out.push(cho.into());
@@ -185,7 +244,7 @@ impl CharacterState {
         }
     }
 
-    pub fn commit(&self, out: &mut String) {
+    pub fn commit(&self, ksx1001_only: bool, out: &mut String) {
         match (self.cho, self.jung, self.jong) {
             (None, None, None) => {}
             (None, Some(jung), Some(jong)) => {
@@ -196,7 +255,9 @@ impl CharacterState {
                 out.push(cho.jamo());
                 out.push(jong.jamo());
             }
-            (Some(cho), Some(jung), jong) => out.push(cho.compose(jung, jong)),
+            (Some(cho), Some(jung), jong) => {
+                Self::push_syllable(cho, jung, jong, ksx1001_only, out)
+            }
             (Some(cho), None, None) => out.push(cho.jamo()),
             (None, Some(jung), None) => out.push(jung.jamo()),
             (None, None, Some(jong)) => out.push(jong.jamo()),
@@ -543,7 +604,7 @@ mod tests {
             jong: Some(Jongseong::Digeut),
         };
         let mut out = String::new();
-        state.preedit(PreeditJohabLevel::Needed, &mut out);
+        state.preedit(PreeditJohabLevel::Needed, false, &mut out);
         assert_eq!(out, "ᄂᅠᆮ");
     }
 }
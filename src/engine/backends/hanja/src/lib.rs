@@ -1,14 +1,48 @@
 use kime_engine_backend::{
     InputEngineMode,
     InputEngineModeResult::{self, Continue, Exit, ExitHandled},
-    Key,
+    Key, KeyCode,
 };
 
 use kime_engine_candidate::client::Client;
 
+/// Number of candidates shown in one page
+pub const CANDIDATE_PAGE_SIZE: usize = 10;
+
+/// Candidate list drawn by frontend itself instead of `kime-candidate-window`
+#[derive(Debug)]
+struct CandidateList {
+    key: String,
+    entries: &'static [(&'static str, &'static str)],
+    cursor: usize,
+}
+
+impl CandidateList {
+    fn select(&self, index: usize, commit_buf: &mut String) -> bool {
+        match self.entries.get(index) {
+            Some((hanja, _)) => {
+                commit_buf.push_str(hanja);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn page_start(&self) -> usize {
+        self.cursor - self.cursor % CANDIDATE_PAGE_SIZE
+    }
+
+    fn move_cursor(&mut self, offset: isize) {
+        let max = self.entries.len().saturating_sub(1);
+        self.cursor = (self.cursor as isize + offset).clamp(0, max as isize) as usize;
+    }
+}
+
 #[derive(Debug)]
 pub struct HanjaMode {
     client: Option<Client>,
+    candidates: Option<CandidateList>,
+    frontend_candidate: bool,
 }
 
 impl Default for HanjaMode {
@@ -19,11 +53,29 @@ impl Default for HanjaMode {
 
 impl HanjaMode {
     pub fn new() -> Self {
-        Self { client: None }
+        Self {
+            client: None,
+            candidates: None,
+            frontend_candidate: false,
+        }
+    }
+
+    /// Let frontend draw candidate list instead of spawning candidate window
+    pub fn set_frontend_candidate(&mut self, frontend_candidate: bool) {
+        self.frontend_candidate = frontend_candidate;
     }
 
     pub fn set_key(&mut self, key: &str) -> bool {
         if let Some(entries) = kime_engine_dict::lookup(key) {
+            if self.frontend_candidate {
+                self.candidates = Some(CandidateList {
+                    key: key.into(),
+                    entries,
+                    cursor: 0,
+                });
+                return true;
+            }
+
             match Client::new(entries) {
                 Ok(client) => {
                     self.client = Some(client);
@@ -35,24 +87,103 @@ impl HanjaMode {
             false
         }
     }
+
+    /// Candidate entries and selected index
+    pub fn candidates(&self) -> Option<(&'static [(&'static str, &'static str)], usize)> {
+        self.candidates.as_ref().map(|c| (c.entries, c.cursor))
+    }
+
+    fn press_candidate_key(
+        list: &mut CandidateList,
+        key: Key,
+        commit_buf: &mut String,
+    ) -> InputEngineModeResult<bool> {
+        if !key.state.is_empty() {
+            commit_buf.push_str(&list.key);
+            return Exit;
+        }
+
+        let number = match key.code {
+            KeyCode::One => Some(0),
+            KeyCode::Two => Some(1),
+            KeyCode::Three => Some(2),
+            KeyCode::Four => Some(3),
+            KeyCode::Five => Some(4),
+            KeyCode::Six => Some(5),
+            KeyCode::Seven => Some(6),
+            KeyCode::Eight => Some(7),
+            KeyCode::Nine => Some(8),
+            KeyCode::Zero => Some(9),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            return if list.select(list.page_start() + number, commit_buf) {
+                ExitHandled(true)
+            } else {
+                Continue(true)
+            };
+        }
+
+        match key.code {
+            KeyCode::Up => list.move_cursor(-1),
+            KeyCode::Down => list.move_cursor(1),
+            KeyCode::Left | KeyCode::PageUp => list.move_cursor(-(CANDIDATE_PAGE_SIZE as isize)),
+            KeyCode::Right | KeyCode::PageDown => list.move_cursor(CANDIDATE_PAGE_SIZE as isize),
+            KeyCode::Enter | KeyCode::Space | KeyCode::Tab => {
+                list.select(list.cursor, commit_buf);
+                return ExitHandled(true);
+            }
+            KeyCode::Esc | KeyCode::Backspace => {
+                commit_buf.push_str(&list.key);
+                return ExitHandled(true);
+            }
+            _ => {
+                commit_buf.push_str(&list.key);
+                return Exit;
+            }
+        }
+
+        Continue(true)
+    }
 }
 
 impl InputEngineMode for HanjaMode {
     type ConfigData = ();
 
-    fn press_key(&mut self, _: &(), _: Key, _: &mut String) -> InputEngineModeResult<bool> {
+    fn press_key(
+        &mut self,
+        _: &(),
+        key: Key,
+        commit_buf: &mut String,
+    ) -> InputEngineModeResult<bool> {
+        if let Some(list) = self.candidates.as_mut() {
+            return Self::press_candidate_key(list, key, commit_buf);
+        }
+
         self.reset();
 
         Exit
     }
 
-    fn preedit_str(&self, _: &mut String) {}
+    fn preedit_str(&self, buf: &mut String) {
+        if let Some(list) = self.candidates.as_ref() {
+            buf.push_str(&list.key);
+        }
+    }
 
-    fn clear_preedit(&mut self, _: &mut String) -> InputEngineModeResult<()> {
-        Continue(())
+    fn clear_preedit(&mut self, commit_buf: &mut String) -> InputEngineModeResult<()> {
+        match self.candidates.take() {
+            Some(list) => {
+                commit_buf.push_str(&list.key);
+                ExitHandled(())
+            }
+            None => Continue(()),
+        }
     }
 
     fn reset(&mut self) -> InputEngineModeResult<()> {
+        self.candidates = None;
         self.client.take().and_then(|c| c.close().ok());
 
         ExitHandled(())
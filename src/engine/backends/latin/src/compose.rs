@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+/// Max depth of `include` in compose files
+const MAX_INCLUDE_DEPTH: usize = 8;
+const SYSTEM_LOCALE_DIR: &str = "/usr/share/X11/locale";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ComposeSym {
+    /// `Multi_key` which is pressed by `compose_key`
+    Multi,
+    /// Char of key, dead keys are combining characters e.g. `dead_acute` is U+0301
+    Char(char),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ComposeMatch<'a> {
+    /// Sequence is not in table
+    None,
+    /// Sequence can be continued
+    Prefix,
+    /// Sequence is completed
+    Exact(&'a str),
+}
+
+/// Sequences of XCompose files
+#[derive(Clone, Default)]
+pub struct ComposeTable {
+    sequences: BTreeMap<Vec<ComposeSym>, String>,
+}
+
+impl ComposeTable {
+    /// Load compose file like Xlib, `XCOMPOSEFILE` then `~/.XCompose` then file of current locale
+    pub fn load_default() -> Self {
+        let mut table = Self::default();
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+
+        let path = std::env::var_os("XCOMPOSEFILE")
+            .map(PathBuf::from)
+            .or_else(|| {
+                home.as_ref()
+                    .map(|home| home.join(".XCompose"))
+                    .filter(|path| path.exists())
+            })
+            .or_else(locale_compose_file);
+
+        if let Some(path) = path {
+            table.load_file(&path, 0);
+        }
+
+        table
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+
+    pub fn lookup(&self, sequence: &[ComposeSym]) -> ComposeMatch<'_> {
+        // Conflicting sequences are removed by `insert` so exact one can't be a prefix
+        match self
+            .sequences
+            .range::<[ComposeSym], _>((Bound::Included(sequence), Bound::Unbounded))
+            .next()
+        {
+            Some((k, v)) if k.as_slice() == sequence => ComposeMatch::Exact(v),
+            Some((k, _)) if k.starts_with(sequence) => ComposeMatch::Prefix,
+            _ => ComposeMatch::None,
+        }
+    }
+
+    /// Later definition replaces conflicting ones like libX11,
+    /// which are prefixes of sequence or have sequence as prefix
+    fn insert(&mut self, sequence: Vec<ComposeSym>, result: String) {
+        for len in 1..sequence.len() {
+            self.sequences.remove(&sequence[..len]);
+        }
+
+        let longer: Vec<_> = self
+            .sequences
+            .range::<[ComposeSym], _>((Bound::Excluded(sequence.as_slice()), Bound::Unbounded))
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(&sequence))
+            .cloned()
+            .collect();
+
+        for k in longer {
+            self.sequences.remove(&k);
+        }
+
+        self.sequences.insert(sequence, result);
+    }
+
+    /// Parse content of compose file, `include` is resolved with filesystem
+    pub fn parse(&mut self, content: &str) {
+        self.parse_impl(content, 0);
+    }
+
+    fn load_file(&mut self, path: &Path, depth: usize) {
+        if depth > MAX_INCLUDE_DEPTH {
+            return;
+        }
+
+        if let Ok(content) = std::fs::read_to_string(path) {
+            self.parse_impl(&content, depth);
+        }
+    }
+
+    fn parse_impl(&mut self, content: &str, depth: usize) {
+        let mut skipped = 0;
+
+        for line in content.lines() {
+            let line = line.trim_start();
+
+            if let Some(include) = line.strip_prefix("include") {
+                if let Some(path) = parse_string(include.trim_start()).and_then(|s| expand(&s)) {
+                    self.load_file(&path, depth + 1);
+                }
+            } else if let Some((sequence, result)) = parse_sequence(line) {
+                self.insert(sequence, result);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                skipped += 1;
+            }
+        }
+
+        if skipped > 0 {
+            log::debug!("Skipped {} unsupported compose sequences", skipped);
+        }
+    }
+}
+
+/// Parse `<dead_acute> <e> : "é" eacute`, lines which have unknown keysym or modifiers are ignored
+fn parse_sequence(line: &str) -> Option<(Vec<ComposeSym>, String)> {
+    let (lhs, rhs) = line.split_once(':')?;
+    let mut sequence = Vec::new();
+
+    for token in lhs.split_whitespace() {
+        let name = token.strip_prefix('<')?.strip_suffix('>')?;
+        sequence.push(keysym_to_sym(name)?);
+    }
+
+    if sequence.is_empty() {
+        return None;
+    }
+
+    Some((sequence, parse_string(rhs.trim_start())?))
+}
+
+/// Parse quoted string with escapes, octal and hex escapes are bytes of utf8
+fn parse_string(s: &str) -> Option<String> {
+    let mut chars = s.strip_prefix('"')?.chars().peekable();
+    let mut ret = Vec::new();
+
+    loop {
+        match chars.next()? {
+            '"' => return String::from_utf8(ret).ok(),
+            '\\' => {
+                let (radix, max_len) = match chars.peek()? {
+                    'x' | 'X' => {
+                        chars.next();
+                        (16, 2)
+                    }
+                    '0'..='7' => (8, 3),
+                    'n' => {
+                        chars.next();
+                        ret.push(b'\n');
+                        continue;
+                    }
+                    _ => {
+                        let c = chars.next()?;
+                        ret.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        continue;
+                    }
+                };
+
+                let mut code = 0;
+                for _ in 0..max_len {
+                    match chars.peek().and_then(|c| c.to_digit(radix)) {
+                        Some(d) => {
+                            code = code * radix + d;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                ret.push(u8::try_from(code).ok()?);
+            }
+            c => ret.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+/// Expand `%H`, `%L` and `%S` of include path
+fn expand(path: &str) -> Option<PathBuf> {
+    if path == "%L" {
+        return locale_compose_file();
+    }
+
+    let mut ret = String::new();
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            match chars.next()? {
+                'H' => ret.push_str(std::env::var("HOME").ok()?.as_str()),
+                'S' => ret.push_str(SYSTEM_LOCALE_DIR),
+                'L' => ret.push_str(locale_compose_file()?.to_str()?),
+                c => ret.push(c),
+            }
+        } else {
+            ret.push(c);
+        }
+    }
+
+    Some(ret.into())
+}
+
+/// System compose file of current locale from `compose.dir`
+fn locale_compose_file() -> Option<PathBuf> {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty())
+        .unwrap_or_else(|| "en_US.UTF-8".into());
+    let dir = Path::new(SYSTEM_LOCALE_DIR);
+    let compose_dir = std::fs::read_to_string(dir.join("compose.dir")).ok()?;
+
+    let find = |locale: &str| {
+        compose_dir.lines().find_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let file = tokens.next()?.trim_end_matches(':');
+            if tokens.next()? == locale {
+                Some(dir.join(file))
+            } else {
+                None
+            }
+        })
+    };
+
+    find(&locale).or_else(|| find("en_US.UTF-8"))
+}
+
+fn keysym_to_sym(name: &str) -> Option<ComposeSym> {
+    let mut chars = name.chars();
+
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(ComposeSym::Char(c));
+    }
+
+    if let Some(code) = name.strip_prefix('U') {
+        if let Some(c) = u32::from_str_radix(code, 16).ok().and_then(char::from_u32) {
+            return Some(ComposeSym::Char(c));
+        }
+    }
+
+    let c = match name {
+        "Multi_key" => return Some(ComposeSym::Multi),
+        "dead_grave" => '\u{0300}',
+        "dead_acute" => '\u{0301}',
+        "dead_circumflex" => '\u{0302}',
+        "dead_tilde" => '\u{0303}',
+        "dead_macron" => '\u{0304}',
+        "dead_breve" => '\u{0306}',
+        "dead_abovedot" => '\u{0307}',
+        "dead_diaeresis" => '\u{0308}',
+        "dead_hook" => '\u{0309}',
+        "dead_abovering" => '\u{030A}',
+        "dead_doubleacute" => '\u{030B}',
+        "dead_caron" => '\u{030C}',
+        "dead_horn" => '\u{031B}',
+        "dead_belowdot" => '\u{0323}',
+        "dead_belowcomma" => '\u{0326}',
+        "dead_cedilla" => '\u{0327}',
+        "dead_ogonek" => '\u{0328}',
+        "dead_stroke" => '\u{0338}',
+        "dead_iota" => '\u{0345}',
+        "space" => ' ',
+        "exclam" => '!',
+        "quotedbl" => '"',
+        "numbersign" => '#',
+        "dollar" => '$',
+        "percent" => '%',
+        "ampersand" => '&',
+        "apostrophe" => '\'',
+        "parenleft" => '(',
+        "parenright" => ')',
+        "asterisk" => '*',
+        "plus" => '+',
+        "comma" => ',',
+        "minus" => '-',
+        "period" => '.',
+        "slash" => '/',
+        "colon" => ':',
+        "semicolon" => ';',
+        "less" => '<',
+        "equal" => '=',
+        "greater" => '>',
+        "question" => '?',
+        "at" => '@',
+        "bracketleft" => '[',
+        "backslash" => '\\',
+        "bracketright" => ']',
+        "asciicircum" => '^',
+        "underscore" => '_',
+        "grave" => '`',
+        "braceleft" => '{',
+        "bar" => '|',
+        "braceright" => '}',
+        "asciitilde" => '~',
+        "nobreakspace" => '\u{00A0}',
+        "acute" => '´',
+        "diaeresis" => '¨',
+        "cedilla" => '¸',
+        "degree" => '°',
+        "macron" => '¯',
+        _ => return None,
+    };
+
+    Some(ComposeSym::Char(c))
+}
+
+/// Dead keys are written as combining characters in layout
+pub fn is_dead_key(c: char) -> bool {
+    ('\u{0300}'..='\u{036F}').contains(&c)
+}
@@ -1,69 +1,171 @@
-use kime_engine_backend::{InputEngineBackend, Key, KeyMap};
-use serde::{Deserialize, Serialize};
+mod compose;
 
-#[derive(Serialize, Deserialize)]
-pub enum LatinLayout {
-    Qwerty,
-    Dvorak,
-    Colemak,
-}
+use std::borrow::Cow;
 
-impl Default for LatinLayout {
-    fn default() -> Self {
-        Self::Qwerty
-    }
-}
+use kime_engine_backend::{CapsLockBehavior, InputEngineBackend, Key, KeyCode, KeyMap};
+use serde::{Deserialize, Serialize};
+
+pub use compose::{ComposeMatch, ComposeSym, ComposeTable};
 
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
 pub struct LatinConfig {
-    pub layout: LatinLayout,
+    pub layout: String,
     pub preferred_direct: bool,
+    pub capslock_behavior: CapsLockBehavior,
+    /// Load XCompose files for dead keys and compose key
+    pub compose: bool,
+    /// Key which works as `Multi_key` of XCompose
+    pub compose_key: Option<Key>,
 }
 
 impl Default for LatinConfig {
     fn default() -> Self {
         Self {
-            layout: LatinLayout::Qwerty,
+            layout: "Qwerty".into(),
             preferred_direct: true,
+            capslock_behavior: CapsLockBehavior::Letters,
+            compose: false,
+            compose_key: None,
         }
     }
 }
 
+pub const BUILTIN_LAYOUTS: &[(&str, &str)] = &[
+    ("Qwerty", include_str!("../data/qwerty.yaml")),
+    ("Dvorak", include_str!("../data/dvorak.yaml")),
+    ("Colemak", include_str!("../data/colemak.yaml")),
+];
+
 pub struct LatinData {
     keymap: KeyMap<char>,
+    capslock_behavior: CapsLockBehavior,
+    compose_table: ComposeTable,
+    compose_key: Option<Key>,
+}
+
+impl Default for LatinData {
+    fn default() -> Self {
+        Self::new(&LatinConfig::default(), builtin_layouts())
+    }
 }
 
 impl LatinData {
-    pub fn new(config: &LatinConfig) -> Self {
+    #[cfg(unix)]
+    pub fn from_config_with_dir(config: &LatinConfig, dir: &xdg::BaseDirectories) -> Self {
+        let custom_layouts = dir
+            .list_config_files("latin_layouts")
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?;
+
+                let content = match std::fs::read_to_string(&path) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        log::error!("Can't read latin layout {}: {}", path.display(), err);
+                        return None;
+                    }
+                };
+
+                match load_layout(&content) {
+                    Ok(layout) => Some((name.to_string().into(), layout)),
+                    Err(err) => {
+                        log::error!("Can't parse latin layout {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            });
+
+        let mut data = Self::new(config, custom_layouts.chain(builtin_layouts()));
+
+        if config.compose {
+            data.set_compose_table(ComposeTable::load_default());
+        }
+
+        data
+    }
+
+    /// Unknown layout falls back to qwerty
+    pub fn new(
+        config: &LatinConfig,
+        mut layouts: impl Iterator<Item = (Cow<'static, str>, KeyMap<char>)>,
+    ) -> Self {
+        let keymap = layouts
+            .find_map(|(name, layout)| {
+                if name == config.layout {
+                    Some(layout)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| load_layout(BUILTIN_LAYOUTS[0].1).ok())
+            .unwrap_or_default();
+
         Self {
-            keymap: load_layout(config),
+            keymap,
+            capslock_behavior: config.capslock_behavior,
+            compose_table: ComposeTable::default(),
+            compose_key: config.compose_key,
         }
     }
 
+    pub fn set_compose_table(&mut self, table: ComposeTable) {
+        self.compose_table = table;
+    }
+
+    pub const fn capslock_behavior(&self) -> CapsLockBehavior {
+        self.capslock_behavior
+    }
+
     #[inline]
     pub fn lookup(&self, key: Key) -> Option<char> {
         self.keymap.get(key)
     }
 }
 
-fn load_layout(config: &LatinConfig) -> KeyMap<char> {
-    let layout = match config.layout {
-        LatinLayout::Qwerty => include_str!("../data/qwerty.yaml"),
-        LatinLayout::Dvorak => include_str!("../data/dvorak.yaml"),
-        LatinLayout::Colemak => include_str!("../data/colemak.yaml"),
-    };
-    serde_yaml::from_str(layout).unwrap_or_default()
+pub fn load_layout(content: &str) -> Result<KeyMap<char>, serde_yaml::Error> {
+    serde_yaml::from_str(content)
+}
+
+pub fn builtin_layouts() -> impl Iterator<Item = (Cow<'static, str>, KeyMap<char>)> {
+    BUILTIN_LAYOUTS
+        .iter()
+        .copied()
+        .filter_map(|(name, layout)| load_layout(layout).ok().map(|l| (name.into(), l)))
 }
 
 #[derive(Clone)]
 pub struct LatinEngine {
     preferred_direct: bool,
+    /// Pressed keys of current compose sequence
+    compose: Vec<ComposeSym>,
 }
 
 impl LatinEngine {
     pub fn new(preferred_direct: bool) -> Self {
-        Self { preferred_direct }
+        Self {
+            preferred_direct,
+            compose: Vec::new(),
+        }
+    }
+
+    fn compose_key(&mut self, config: &LatinData, sym: ComposeSym, commit_buf: &mut String) {
+        self.compose.push(sym);
+
+        match config.compose_table.lookup(&self.compose) {
+            ComposeMatch::Prefix => {}
+            ComposeMatch::Exact(s) => {
+                commit_buf.push_str(s);
+                self.compose.clear();
+            }
+            // Cancel sequence and type the key which doesn't match
+            ComposeMatch::None => {
+                self.compose.clear();
+                if let ComposeSym::Char(ch) = sym {
+                    commit_buf.push(ch);
+                }
+            }
+        }
     }
 }
 
@@ -72,23 +174,64 @@ impl InputEngineBackend for LatinEngine {
 
     fn press_key(&mut self, config: &LatinData, key: Key, commit_buf: &mut String) -> bool {
         if self.preferred_direct {
-            false
-        } else {
-            if let Some(ch) = config.lookup(key) {
+            return false;
+        }
+
+        if config.compose_key == Some(key) && !config.compose_table.is_empty() {
+            self.compose_key(config, ComposeSym::Multi, commit_buf);
+            return true;
+        }
+
+        if !self.compose.is_empty() && key.code == KeyCode::Backspace {
+            self.compose.pop();
+            return true;
+        }
+
+        match config.lookup(key) {
+            Some(ch) if !self.compose.is_empty() => {
+                self.compose_key(config, ComposeSym::Char(ch), commit_buf);
+                true
+            }
+            Some(ch)
+                if compose::is_dead_key(ch)
+                    && config.compose_table.lookup(&[ComposeSym::Char(ch)])
+                        == ComposeMatch::Prefix =>
+            {
+                self.compose.push(ComposeSym::Char(ch));
+                true
+            }
+            Some(ch) => {
                 commit_buf.push(ch);
                 true
-            } else {
-                false
             }
+            None => false,
         }
     }
 
-    fn clear_preedit(&mut self, _commit_buf: &mut String) {}
-    fn reset(&mut self) {}
+    // Incomplete sequence has nothing to commit
+    fn clear_preedit(&mut self, _commit_buf: &mut String) {
+        self.compose.clear();
+    }
+
+    fn reset(&mut self) {
+        self.compose.clear();
+    }
 
     fn has_preedit(&self) -> bool {
-        false
+        !self.compose.is_empty()
     }
 
-    fn preedit_str(&self, _buf: &mut String) {}
+    fn preedit_str(&self, buf: &mut String) {
+        for sym in self.compose.iter() {
+            match *sym {
+                ComposeSym::Multi => buf.push('⎄'),
+                // Show dead key on dotted circle
+                ComposeSym::Char(ch) if compose::is_dead_key(ch) => {
+                    buf.push('◌');
+                    buf.push(ch);
+                }
+                ComposeSym::Char(ch) => buf.push(ch),
+            }
+        }
+    }
 }
@@ -2,11 +2,14 @@
 
 pub use kime_engine_core::{
     load_engine_config_from_config_dir, load_other_configs_from_config_dir, Config, DaemonConfig,
-    DaemonModule, IconColor, IndicatorConfig, InputCategory, InputEngine, InputResult, LogConfig,
-    ModifierState,
+    DaemonModule, IconColor, IndicatorConfig, InputCategory, InputEngine, InputMode, InputResult,
+    KeyMatching, LogConfig, ModifierState,
 };
 
-pub const KIME_API_VERSION: usize = 7;
+pub const KIME_API_VERSION: usize = 17;
+
+/// Number of candidates shown in one page, it's renamed in bindings by cbindgen config
+pub use kime_engine_core::CANDIDATE_PAGE_SIZE as KIME_CANDIDATE_PAGE_SIZE;
 
 #[repr(C)]
 pub struct RustStr {
@@ -38,6 +41,12 @@ impl RustSlice {
     }
 }
 
+#[repr(C)]
+pub struct Candidate {
+    value: RustStr,
+    description: RustStr,
+}
+
 #[repr(C)]
 pub struct FontData {
     font_data: RustSlice,
@@ -66,6 +75,85 @@ pub extern "C" fn kime_engine_set_input_category(
     engine.set_input_category(category);
 }
 
+/// Get current input category
+#[no_mangle]
+pub extern "C" fn kime_engine_input_category(engine: &InputEngine) -> InputCategory {
+    engine.category()
+}
+
+/// Apply profile of focused app
+///
+/// `app` is window class or app id of focused app
+///
+/// # Safety
+///
+/// `app` must be valid for `app_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn kime_engine_set_app(
+    engine: &mut InputEngine,
+    config: &Config,
+    app: *const u8,
+    app_len: usize,
+) -> InputResult {
+    let app = std::str::from_utf8(std::slice::from_raw_parts(app, app_len)).unwrap_or_default();
+    engine.set_app(app, config)
+}
+
+/// Get current input mode
+///
+/// ## Return
+///
+/// `false` when engine is not in any mode
+#[no_mangle]
+pub extern "C" fn kime_engine_input_mode(engine: &InputEngine, mode: &mut InputMode) -> bool {
+    match engine.input_mode() {
+        Some(m) => {
+            *mode = m;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Let frontend draw candidate list instead of spawning candidate window
+#[no_mangle]
+pub extern "C" fn kime_engine_set_frontend_candidate(
+    engine: &mut InputEngine,
+    frontend_candidate: bool,
+) {
+    engine.set_frontend_candidate(frontend_candidate);
+}
+
+/// Get length of candidate list
+#[no_mangle]
+pub extern "C" fn kime_engine_candidate_len(engine: &InputEngine) -> usize {
+    engine.candidates().map_or(0, |(entries, _)| entries.len())
+}
+
+/// Get index of selected candidate
+#[no_mangle]
+pub extern "C" fn kime_engine_candidate_cursor(engine: &InputEngine) -> usize {
+    engine.candidates().map_or(0, |(_, cursor)| cursor)
+}
+
+/// Get candidate at index
+///
+/// ## Return
+///
+/// empty strings when index is out of bounds
+#[no_mangle]
+pub extern "C" fn kime_engine_candidate(engine: &InputEngine, index: usize) -> Candidate {
+    let (value, description) = engine
+        .candidates()
+        .and_then(|(entries, _)| entries.get(index).copied())
+        .unwrap_or(("", ""));
+
+    Candidate {
+        value: RustStr::new(value),
+        description: RustStr::new(description),
+    }
+}
+
 /// Delete engine
 ///
 /// # Safety
@@ -114,6 +202,14 @@ pub extern "C" fn kime_engine_preedit_str(engine: &mut InputEngine) -> RustStr {
     RustStr::new(engine.preedit_str())
 }
 
+/// Get byte offset of preedit string where composing part starts
+///
+/// Part before it is composed but not committed yet
+#[no_mangle]
+pub extern "C" fn kime_engine_preedit_composing_start(engine: &InputEngine) -> usize {
+    engine.preedit_composing_start()
+}
+
 /// Clear commit string
 #[no_mangle]
 pub extern "C" fn kime_engine_clear_commit(engine: &mut InputEngine) {
@@ -132,6 +228,26 @@ pub extern "C" fn kime_engine_remove_preedit(engine: &mut InputEngine) {
     engine.remove_preedit();
 }
 
+/// Handle focus in
+///
+/// ## Return
+///
+/// input result which has preedit kept by `on_focus_out`
+#[no_mangle]
+pub extern "C" fn kime_engine_focus_in(engine: &mut InputEngine) -> InputResult {
+    engine.focus_in()
+}
+
+/// Handle focus out with `on_focus_out` config
+///
+/// ## Return
+///
+/// input result
+#[no_mangle]
+pub extern "C" fn kime_engine_focus_out(engine: &mut InputEngine, config: &Config) -> InputResult {
+    engine.focus_out(config)
+}
+
 /// Reset engine state
 #[no_mangle]
 pub extern "C" fn kime_engine_reset(engine: &mut InputEngine) {
@@ -148,10 +264,85 @@ pub extern "C" fn kime_engine_press_key(
     engine: &mut InputEngine,
     config: &Config,
     hardware_code: u16,
-    numlock: bool,
     state: ModifierState,
 ) -> InputResult {
-    engine.press_key_code(hardware_code, state, numlock, config)
+    engine.press_key_code(hardware_code, state, config)
+}
+
+/// Press key from X keysym when modifier state
+///
+/// ## Return
+///
+/// input result
+#[no_mangle]
+pub extern "C" fn kime_engine_press_keysym(
+    engine: &mut InputEngine,
+    config: &Config,
+    keysym: u32,
+    state: ModifierState,
+) -> InputResult {
+    engine.press_keysym(keysym, state, config)
+}
+
+/// Release key when modifier state, release is never consumed
+///
+/// ## Return
+///
+/// input result
+#[no_mangle]
+pub extern "C" fn kime_engine_release_key(
+    engine: &mut InputEngine,
+    config: &Config,
+    hardware_code: u16,
+    state: ModifierState,
+) -> InputResult {
+    engine.release_key_code(hardware_code, state, config)
+}
+
+/// Release key from X keysym when modifier state, release is never consumed
+///
+/// ## Return
+///
+/// input result
+#[no_mangle]
+pub extern "C" fn kime_engine_release_keysym(
+    engine: &mut InputEngine,
+    config: &Config,
+    keysym: u32,
+    state: ModifierState,
+) -> InputResult {
+    engine.release_keysym(keysym, state, config)
+}
+
+/// Get milliseconds until held keys should be flushed by `kime_engine_flush_held_keys`
+///
+/// ## Return
+///
+/// `false` when engine doesn't hold any key
+#[no_mangle]
+pub extern "C" fn kime_engine_held_timeout(
+    engine: &InputEngine,
+    config: &Config,
+    timeout: &mut u32,
+) -> bool {
+    match engine.held_deadline(config) {
+        Some(deadline) => {
+            let remain = deadline.saturating_duration_since(std::time::Instant::now());
+            *timeout = remain.as_millis() as u32;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Flush held keys when hotkey is not completed in time
+///
+/// ## Return
+///
+/// input result, frontend should process held keys again when it has `REPLAY`
+#[no_mangle]
+pub extern "C" fn kime_engine_flush_held_keys(engine: &mut InputEngine) -> InputResult {
+    engine.flush_held_keys()
 }
 
 /// Load config from local file
@@ -187,6 +378,12 @@ pub extern "C" fn kime_config_candidate_font(config: &Config) -> FontData {
     }
 }
 
+/// Get key_matching config
+#[no_mangle]
+pub extern "C" fn kime_config_key_matching(config: &Config) -> KeyMatching {
+    config.key_matching
+}
+
 /// Get xim_preedit_font config
 /// font_data only valid while config is live
 #[no_mangle]
@@ -200,6 +397,21 @@ pub extern "C" fn kime_config_xim_preedit_font(config: &Config) -> FontData {
     }
 }
 
+/// Find system font which has glyph of `c`, font_data is empty when there's no such font
+/// font_data only valid while config is live
+#[no_mangle]
+pub extern "C" fn kime_config_fallback_font(config: &Config, c: u32) -> FontData {
+    let (font, index) = char::from_u32(c)
+        .and_then(|c| config.fallback_fonts.find(c))
+        .unwrap_or_default();
+
+    FontData {
+        font_data: RustSlice::new(font),
+        index,
+        size: 0.,
+    }
+}
+
 /// Load daemon config
 #[cfg(unix)]
 #[no_mangle]
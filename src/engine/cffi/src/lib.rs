@@ -12,10 +12,12 @@ extern "C" {}
 pub use kime_engine_config::{DaemonModule, EnumSet};
 
 pub use ffi::{
-    IconColor, InputCategory, InputResult, InputResult_CONSUMED, InputResult_HAS_COMMIT,
-    InputResult_HAS_PREEDIT, InputResult_LANGUAGE_CHANGED, InputResult_NOT_READY, ModifierState,
-    ModifierState_ALT, ModifierState_CONTROL, ModifierState_SHIFT, ModifierState_SUPER,
-    KIME_API_VERSION,
+    IconColor, InputCategory, InputMode, InputResult, InputResult_CONSUMED,
+    InputResult_HAS_CANDIDATE, InputResult_HAS_COMMIT, InputResult_HAS_PREEDIT, InputResult_HOLD,
+    InputResult_LANGUAGE_CHANGED, InputResult_NOT_READY, InputResult_REPLAY, KeyMatching,
+    ModifierState, ModifierState_ALT, ModifierState_ALTGR, ModifierState_CAPSLOCK,
+    ModifierState_CONTROL, ModifierState_NUMLOCK, ModifierState_SHIFT, ModifierState_SUPER,
+    KIME_API_VERSION, KIME_CANDIDATE_PAGE_SIZE,
 };
 
 pub fn check_api_version() -> bool {
@@ -49,18 +51,103 @@ impl InputEngine {
         unsafe { ffi::kime_engine_set_input_category(self.engine, category) };
     }
 
+    pub fn input_category(&self) -> InputCategory {
+        unsafe { ffi::kime_engine_input_category(self.engine) }
+    }
+
+    /// Apply profile of focused app which is matched by window class or app id
+    pub fn set_app(&mut self, app: &str, config: &Config) -> InputResult {
+        unsafe { ffi::kime_engine_set_app(self.engine, config.config, app.as_ptr(), app.len()) }
+    }
+
+    pub fn input_mode(&self) -> Option<InputMode> {
+        let mut mode = InputMode::Math;
+        if unsafe { ffi::kime_engine_input_mode(self.engine, &mut mode) } {
+            Some(mode)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_frontend_candidate(&mut self, frontend_candidate: bool) {
+        unsafe { ffi::kime_engine_set_frontend_candidate(self.engine, frontend_candidate) };
+    }
+
+    pub fn candidate_len(&self) -> usize {
+        unsafe { ffi::kime_engine_candidate_len(self.engine) }
+    }
+
+    pub fn candidate_cursor(&self) -> usize {
+        unsafe { ffi::kime_engine_candidate_cursor(self.engine) }
+    }
+
+    pub fn candidate(&self, index: usize) -> (&str, &str) {
+        unsafe {
+            let c = ffi::kime_engine_candidate(self.engine, index);
+            (
+                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                    c.value.ptr,
+                    c.value.len,
+                )),
+                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                    c.description.ptr,
+                    c.description.len,
+                )),
+            )
+        }
+    }
+
     pub fn press_key(
         &mut self,
         config: &Config,
         hardware_code: u16,
-        numlock: bool,
         state: ModifierState,
     ) -> InputResult {
-        unsafe {
-            ffi::kime_engine_press_key(self.engine, config.config, hardware_code, numlock, state)
+        unsafe { ffi::kime_engine_press_key(self.engine, config.config, hardware_code, state) }
+    }
+
+    pub fn press_keysym(
+        &mut self,
+        config: &Config,
+        keysym: u32,
+        state: ModifierState,
+    ) -> InputResult {
+        unsafe { ffi::kime_engine_press_keysym(self.engine, config.config, keysym, state) }
+    }
+
+    pub fn release_key(
+        &mut self,
+        config: &Config,
+        hardware_code: u16,
+        state: ModifierState,
+    ) -> InputResult {
+        unsafe { ffi::kime_engine_release_key(self.engine, config.config, hardware_code, state) }
+    }
+
+    pub fn release_keysym(
+        &mut self,
+        config: &Config,
+        keysym: u32,
+        state: ModifierState,
+    ) -> InputResult {
+        unsafe { ffi::kime_engine_release_keysym(self.engine, config.config, keysym, state) }
+    }
+
+    /// Time until held keys should be flushed, `None` when engine doesn't hold any key
+    pub fn held_timeout(&self, config: &Config) -> Option<std::time::Duration> {
+        let mut timeout = 0;
+
+        if unsafe { ffi::kime_engine_held_timeout(self.engine, config.config, &mut timeout) } {
+            Some(std::time::Duration::from_millis(timeout.into()))
+        } else {
+            None
         }
     }
 
+    pub fn flush_held_keys(&mut self) -> InputResult {
+        unsafe { ffi::kime_engine_flush_held_keys(self.engine) }
+    }
+
     pub fn preedit_str(&mut self) -> &str {
         unsafe {
             let s = ffi::kime_engine_preedit_str(self.engine);
@@ -68,6 +155,11 @@ impl InputEngine {
         }
     }
 
+    /// Byte offset of preedit string where composing part starts
+    pub fn preedit_composing_start(&self) -> usize {
+        unsafe { ffi::kime_engine_preedit_composing_start(self.engine) }
+    }
+
     pub fn commit_str(&self) -> &str {
         unsafe {
             let s = ffi::kime_engine_commit_str(self.engine);
@@ -93,6 +185,14 @@ impl InputEngine {
         }
     }
 
+    pub fn focus_in(&mut self) -> InputResult {
+        unsafe { ffi::kime_engine_focus_in(self.engine) }
+    }
+
+    pub fn focus_out(&mut self, config: &Config) -> InputResult {
+        unsafe { ffi::kime_engine_focus_out(self.engine, config.config) }
+    }
+
     pub fn reset(&mut self) {
         unsafe {
             ffi::kime_engine_reset(self.engine);
@@ -139,6 +239,26 @@ impl Config {
         }
     }
 
+    /// System font which has glyph of `c`
+    pub fn fallback_font(&self, c: char) -> Option<(&[u8], u32)> {
+        unsafe {
+            let font = ffi::kime_config_fallback_font(self.config, c as u32);
+
+            if font.font_data.len == 0 {
+                None
+            } else {
+                Some((
+                    core::slice::from_raw_parts(font.font_data.ptr, font.font_data.len),
+                    font.index,
+                ))
+            }
+        }
+    }
+
+    pub fn key_matching(&self) -> KeyMatching {
+        unsafe { ffi::kime_config_key_matching(self.config) }
+    }
+
     pub fn xim_font(&self) -> (&[u8], u32, f32) {
         unsafe {
             let font = ffi::kime_config_xim_preedit_font(self.config);
@@ -5,9 +5,10 @@ use maplit::btreemap;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::{fmt, str::FromStr};
 
 pub use kime_engine_backend::{Key, KeyCode, ModifierState};
-pub use kime_engine_backend_hangul::{HangulConfig, HangulData};
+pub use kime_engine_backend_hangul::{Addon, HangulConfig, HangulData};
 pub use kime_engine_backend_latin::{LatinConfig, LatinData};
 
 pub use enum_map::{enum_map, EnumMap};
@@ -34,13 +35,23 @@ pub enum InputMode {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum HotkeyBehavior {
     Switch(InputCategory),
     Toggle(InputCategory, InputCategory),
     Mode(InputMode),
     Commit,
     Ignore,
+    /// Switch to category which was active before last switch
+    Previous,
+    /// Switch to next hangul layout
+    CycleLayout,
+    /// Toggle addon of current hangul layout
+    ToggleAddon(Addon),
+    /// Discard preedit
+    Reset,
+    /// Commit preedit and bypass key regardless of result
+    CommitAndBypass,
 }
 
 impl HotkeyBehavior {
@@ -58,25 +69,280 @@ pub enum HotkeyResult {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Hotkey {
-    behavior: HotkeyBehavior,
+    /// Behaviors which run in order, single behavior can be written without list
+    #[cfg_attr(feature = "serde", serde(with = "behaviors"))]
+    behavior: Vec<HotkeyBehavior>,
     result: HotkeyResult,
 }
 
 impl Hotkey {
     pub fn new(behavior: HotkeyBehavior, result: HotkeyResult) -> Self {
-        Self { behavior, result }
+        Self::chain(vec![behavior], result)
+    }
+
+    pub fn chain(behaviors: Vec<HotkeyBehavior>, result: HotkeyResult) -> Self {
+        Self {
+            behavior: behaviors,
+            result,
+        }
     }
 
-    pub const fn behavior(self) -> HotkeyBehavior {
-        self.behavior
+    pub fn behaviors(&self) -> &[HotkeyBehavior] {
+        &self.behavior
     }
-    pub const fn result(self) -> HotkeyResult {
+    pub const fn result(&self) -> HotkeyResult {
         self.result
     }
 }
 
+/// Serialize single behavior without list
+#[cfg(feature = "serde")]
+mod behaviors {
+    use super::HotkeyBehavior;
+    use serde::de::{
+        value::{EnumAccessDeserializer, MapAccessDeserializer, SeqAccessDeserializer},
+        EnumAccess, Error, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    };
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        behaviors: &[HotkeyBehavior],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match behaviors {
+            [behavior] => behavior.serialize(serializer),
+            _ => behaviors.serialize(serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<HotkeyBehavior>, D::Error> {
+        struct BehaviorsVisitor;
+
+        impl<'de> Visitor<'de> for BehaviorsVisitor {
+            type Value = Vec<HotkeyBehavior>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("HotkeyBehavior or list of HotkeyBehavior")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                HotkeyBehavior::deserialize(v.into_deserializer()).map(|b| vec![b])
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+                HotkeyBehavior::deserialize(EnumAccessDeserializer::new(data)).map(|b| vec![b])
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                HotkeyBehavior::deserialize(MapAccessDeserializer::new(map)).map(|b| vec![b])
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq))
+            }
+        }
+
+        deserializer.deserialize_any(BehaviorsVisitor)
+    }
+}
+
+/// Value of translation layer
+#[derive(Clone, Debug, PartialEq)]
+pub enum Translation {
+    /// Another key e.g. `Q: W`
+    Key(Key),
+    /// Run hotkey behaviors and consume key e.g. `CapsLock: {Hotkey: !Toggle [Hangul, Latin]}`
+    Hotkey(Vec<HotkeyBehavior>),
+    /// Commit string and consume key e.g. `N1: !Commit "①"`
+    Commit(String),
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Translation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{EnumAccess, Error, MapAccess, Unexpected, VariantAccess, Visitor};
+
+        #[derive(Deserialize)]
+        enum Kind {
+            Key,
+            Hotkey,
+            Commit,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(transparent)]
+        struct Behaviors(#[serde(with = "behaviors")] Vec<HotkeyBehavior>);
+
+        struct TranslationVisitor;
+
+        impl<'de> Visitor<'de> for TranslationVisitor {
+            type Value = Translation;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("Key, Hotkey or Commit")
+            }
+
+            // Plain string is key for compatibility
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map(Translation::Key)
+                    .map_err(|_| E::invalid_value(Unexpected::Str(v), &"Key"))
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+                let (kind, variant) = data.variant()?;
+
+                match kind {
+                    Kind::Key => variant.newtype_variant().map(Translation::Key),
+                    Kind::Hotkey => variant
+                        .newtype_variant()
+                        .map(|Behaviors(behaviors)| Translation::Hotkey(behaviors)),
+                    Kind::Commit => variant.newtype_variant().map(Translation::Commit),
+                }
+            }
+
+            // Map form is needed for nested enum e.g. `{Hotkey: !Toggle [Hangul, Latin]}`
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let ret = match map.next_key()? {
+                    Some(Kind::Key) => Translation::Key(map.next_value()?),
+                    Some(Kind::Hotkey) => Translation::Hotkey(map.next_value::<Behaviors>()?.0),
+                    Some(Kind::Commit) => Translation::Commit(map.next_value()?),
+                    None => return Err(A::Error::invalid_length(0, &self)),
+                };
+
+                match map.next_key::<Kind>()? {
+                    Some(_) => Err(A::Error::invalid_length(2, &self)),
+                    None => Ok(ret),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(TranslationVisitor)
+    }
+}
+
+/// Key event which triggers hotkey
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HotkeyTrigger {
+    /// Press of key e.g. `S-Space`
+    Press(Key),
+    /// Release of key when no other key is pressed after it e.g. `Tap(ShiftL)`
+    Tap(Key),
+    /// Keys pressed one after another in `hotkey_timeout` e.g. `C-X H`, `Esc Esc`
+    Sequence(Vec<Key>),
+    /// Two keys pressed together in `hotkey_timeout` e.g. `J+K`
+    Chord(Key, Key),
+}
+
+impl From<Key> for HotkeyTrigger {
+    fn from(key: Key) -> Self {
+        Self::Press(key)
+    }
+}
+
+impl fmt::Display for HotkeyTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Press(key) => write!(f, "{}", key),
+            Self::Tap(key) => write!(f, "Tap({})", key),
+            Self::Sequence(keys) => {
+                for (i, key) in keys.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", key)?;
+                }
+                Ok(())
+            }
+            Self::Chord(first, second) => write!(f, "{}+{}", first, second),
+        }
+    }
+}
+
+impl FromStr for HotkeyTrigger {
+    type Err = <Key as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(key) = s.strip_prefix("Tap(").and_then(|s| s.strip_suffix(')')) {
+            return key.parse().map(Self::Tap);
+        }
+
+        if let Some((first, second)) = s.split_once('+') {
+            return Ok(Self::Chord(first.parse()?, second.parse()?));
+        }
+
+        let keys = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Key>, _>>()?;
+
+        match keys.as_slice() {
+            [key] => Ok(Self::Press(*key)),
+            // Empty string is error of key
+            [] => s.parse().map(Self::Press),
+            _ => Ok(Self::Sequence(keys)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for HotkeyTrigger {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for HotkeyTrigger {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{Error, Unexpected};
+
+        let s = String::deserialize(deserializer)?;
+
+        s.parse()
+            .map_err(|_| D::Error::invalid_value(Unexpected::Str(&s), &"HotkeyTrigger"))
+    }
+}
+
+/// What to do with preedit when focus is lost
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusOutBehavior {
+    /// Commit preedit to client
+    #[default]
+    Commit,
+    /// Discard preedit
+    Discard,
+    /// Keep preedit and show it again when focus is back
+    Keep,
+}
+
+/// How frontends match key events with layouts and hotkeys
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub enum KeyMatching {
+    /// Use position of key from hardware keycode
+    #[default]
+    Keycode,
+    /// Use symbol of key from keysym which follows system keyboard layout
+    Keysym,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -135,17 +401,39 @@ impl Default for LogConfig {
     }
 }
 
+/// Overrides for app which is matched by window class or app id
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AppProfile {
+    /// Category when app is focused first time
+    pub default_category: Option<InputCategory>,
+    /// Reset category to default category every time app is focused
+    pub reset_on_focus: bool,
+    pub hangul_layout: Option<String>,
+    pub disabled_modes: EnumSet<InputMode>,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(default))]
 pub struct EngineConfig {
     pub translation_layer: Option<PathBuf>,
+    /// Translation layers for specific category override `translation_layer`
+    pub category_translation_layers: BTreeMap<InputCategory, PathBuf>,
+    /// Apply translation layer before matching hotkeys
+    pub translate_hotkeys: bool,
     pub default_category: InputCategory,
     pub global_category_state: bool,
-    pub global_hotkeys: BTreeMap<Key, Hotkey>,
-    pub category_hotkeys: BTreeMap<InputCategory, BTreeMap<Key, Hotkey>>,
-    pub mode_hotkeys: BTreeMap<InputMode, BTreeMap<Key, Hotkey>>,
+    pub on_focus_out: FocusOutBehavior,
+    pub key_matching: KeyMatching,
+    /// Milliseconds between keys of `Sequence` and `Chord` hotkeys
+    pub hotkey_timeout: u64,
+    pub global_hotkeys: BTreeMap<HotkeyTrigger, Hotkey>,
+    pub category_hotkeys: BTreeMap<InputCategory, BTreeMap<HotkeyTrigger, Hotkey>>,
+    pub mode_hotkeys: BTreeMap<InputMode, BTreeMap<HotkeyTrigger, Hotkey>>,
     pub candidate_font: String,
     pub xim_preedit_font: (String, f32),
+    pub app_profiles: BTreeMap<String, AppProfile>,
     pub latin: LatinConfig,
     pub hangul: HangulConfig,
 }
@@ -154,42 +442,48 @@ impl Default for EngineConfig {
     fn default() -> Self {
         Self {
             translation_layer: None,
+            category_translation_layers: BTreeMap::new(),
+            translate_hotkeys: false,
             latin: LatinConfig::default(),
             hangul: HangulConfig::default(),
             default_category: InputCategory::Latin,
             global_category_state: false,
+            on_focus_out: FocusOutBehavior::default(),
+            key_matching: KeyMatching::default(),
+            hotkey_timeout: 500,
             global_hotkeys: btreemap! {
-                Key::normal(KeyCode::Esc) => Hotkey::new(HotkeyBehavior::Switch(InputCategory::Latin), HotkeyResult::Bypass),
-                Key::normal(KeyCode::AltR) => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
-                Key::normal(KeyCode::Hangul) => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
-                Key::super_(KeyCode::Space) => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
-                Key::normal(KeyCode::Muhenkan) => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
-                Key::new(KeyCode::E, ModifierState::CONTROL | ModifierState::ALT) => Hotkey::new(HotkeyBehavior::Mode(InputMode::Emoji), HotkeyResult::ConsumeIfProcessed),
-                Key::new(KeyCode::Backslash, ModifierState::CONTROL | ModifierState::ALT) => Hotkey::new(HotkeyBehavior::Mode(InputMode::Math), HotkeyResult::ConsumeIfProcessed),
+                Key::normal(KeyCode::Esc).into() => Hotkey::new(HotkeyBehavior::Switch(InputCategory::Latin), HotkeyResult::Bypass),
+                Key::normal(KeyCode::AltR).into() => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+                Key::normal(KeyCode::Hangul).into() => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+                Key::super_(KeyCode::Space).into() => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+                Key::normal(KeyCode::Muhenkan).into() => Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+                Key::new(KeyCode::E, ModifierState::CONTROL | ModifierState::ALT).into() => Hotkey::new(HotkeyBehavior::Mode(InputMode::Emoji), HotkeyResult::ConsumeIfProcessed),
+                Key::new(KeyCode::Backslash, ModifierState::CONTROL | ModifierState::ALT).into() => Hotkey::new(HotkeyBehavior::Mode(InputMode::Math), HotkeyResult::ConsumeIfProcessed),
             },
             category_hotkeys: btreemap! {
                 InputCategory::Hangul => btreemap! {
-                    Key::normal(KeyCode::F9) => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::ConsumeIfProcessed),
-                    Key::normal(KeyCode::HangulHanja) => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::Consume),
-                    Key::normal(KeyCode::ControlR) => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::Consume),
+                    Key::normal(KeyCode::F9).into() => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::HangulHanja).into() => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::Consume),
+                    Key::normal(KeyCode::ControlR).into() => Hotkey::new(HotkeyBehavior::Mode(InputMode::Hanja), HotkeyResult::Consume),
                 },
             },
             mode_hotkeys: btreemap! {
                 InputMode::Hanja => btreemap! {
-                    Key::normal(KeyCode::Enter) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
-                    Key::normal(KeyCode::Tab) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Enter).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Tab).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
                 },
                 InputMode::Emoji => btreemap! {
-                    Key::normal(KeyCode::Enter) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
-                    Key::normal(KeyCode::Tab) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Enter).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Tab).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
                 },
                 InputMode::Math => btreemap! {
-                    Key::normal(KeyCode::Enter) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
-                    Key::normal(KeyCode::Tab) => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Enter).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
+                    Key::normal(KeyCode::Tab).into() => Hotkey::new(HotkeyBehavior::Commit, HotkeyResult::ConsumeIfProcessed),
                 },
             },
             xim_preedit_font: ("Noto Sans CJK KR".to_string(), 15.0),
             candidate_font: "Noto Sans CJK KR".to_string(),
+            app_profiles: BTreeMap::new(),
         }
     }
 }
@@ -1,18 +1,36 @@
+use crate::font::FallbackFonts;
 use crate::KeyMap;
 use fontdb::{Family, Query};
 pub use kime_engine_config::*;
 use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Preprocessed app profile
+pub struct AppProfileData {
+    pub default_category: Option<InputCategory>,
+    pub reset_on_focus: bool,
+    pub hangul_data: Option<HangulData>,
+    pub disabled_modes: EnumSet<InputMode>,
+}
 
 /// Preprocessed engine config
 pub struct Config {
-    pub translation_layer: Option<KeyMap<Key>>,
+    pub translation_layers: EnumMap<InputCategory, Option<KeyMap<Translation>>>,
+    /// Apply translation layer before matching hotkeys
+    pub translate_hotkeys: bool,
     pub default_category: InputCategory,
     pub global_category_state: bool,
-    pub category_hotkeys: EnumMap<InputCategory, Vec<(Key, Hotkey)>>,
-    pub mode_hotkeys: EnumMap<InputMode, Vec<(Key, Hotkey)>>,
+    pub on_focus_out: FocusOutBehavior,
+    pub key_matching: KeyMatching,
+    pub hotkey_timeout: Duration,
+    pub category_hotkeys: EnumMap<InputCategory, Vec<(HotkeyTrigger, Hotkey)>>,
+    pub mode_hotkeys: EnumMap<InputMode, Vec<(HotkeyTrigger, Hotkey)>>,
     pub candidate_font: (Vec<u8>, u32),
     pub xim_preedit_font: (Vec<u8>, u32, f32),
+    pub fallback_fonts: FallbackFonts,
     pub hangul_data: HangulData,
+    pub app_profiles: Vec<(String, AppProfileData)>,
     pub preferred_direct: bool,
     pub latin_data: LatinData,
 }
@@ -24,7 +42,11 @@ impl Default for Config {
 }
 
 impl Config {
-    fn new_impl(mut engine: EngineConfig, hangul_data: HangulData) -> Self {
+    fn new_impl(
+        mut engine: EngineConfig,
+        latin_data: LatinData,
+        hangul_data: impl Fn(&HangulConfig) -> HangulData,
+    ) -> Self {
         let mut db = fontdb::Database::new();
         db.load_system_fonts();
 
@@ -38,33 +60,41 @@ impl Config {
         };
 
         #[cfg(unix)]
-        let translation_layer: Option<KeyMap<Key>> = engine
-            .translation_layer
-            .and_then(|f| {
-                xdg::BaseDirectories::with_prefix("kime")
-                    .ok()
-                    .and_then(|d| d.find_config_file(f))
-            })
-            .as_ref()
-            .and_then(|f| fs::read_to_string(f.as_path()).ok())
-            .as_ref()
-            .and_then(|content| serde_yaml::from_str(content).ok());
+        let load_translation_layer = |f: &PathBuf| -> Option<KeyMap<Translation>> {
+            xdg::BaseDirectories::with_prefix("kime")
+                .ok()
+                .and_then(|d| d.find_config_file(f))
+                .and_then(|f| fs::read_to_string(f.as_path()).ok())
+                .and_then(|content| serde_yaml::from_str(&content).ok())
+        };
 
         #[cfg(not(unix))]
-        let translation_layer = None;
+        let load_translation_layer = |_: &PathBuf| None;
+
+        let base_hangul = &engine.hangul;
 
         Self {
-            translation_layer: translation_layer,
+            translation_layers: enum_map! {
+                cat => engine
+                    .category_translation_layers
+                    .get(&cat)
+                    .or(engine.translation_layer.as_ref())
+                    .and_then(load_translation_layer),
+            },
+            translate_hotkeys: engine.translate_hotkeys,
             default_category: engine.default_category,
             global_category_state: engine.global_category_state,
+            on_focus_out: engine.on_focus_out,
+            key_matching: engine.key_matching,
+            hotkey_timeout: Duration::from_millis(engine.hotkey_timeout),
             category_hotkeys: enum_map! {
                 cat => {
                     if let Some(map) = engine.category_hotkeys.get_mut(&cat) { for (k, v) in engine.global_hotkeys.iter() {
-                            map.entry(*k).or_insert(*v);
+                            map.entry(k.clone()).or_insert_with(|| v.clone());
                         }
-                        map.iter().map(|(k, v)| (*k, *v)).collect()
+                        map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
                     } else {
-                        engine.global_hotkeys.iter().map(|(k, v)| (*k, *v)).collect()
+                        engine.global_hotkeys.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
                     }
                 }
             },
@@ -72,11 +102,11 @@ impl Config {
                 mode => {
                     if let Some(map) = engine.mode_hotkeys.get_mut(&mode) {
                         for (k, v) in engine.global_hotkeys.iter() {
-                            map.entry(*k).or_insert(*v);
+                            map.entry(k.clone()).or_insert_with(|| v.clone());
                         }
-                        map.iter().map(|(k, v)| (*k, *v)).collect()
+                        map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
                     } else {
-                        engine.global_hotkeys.iter().map(|(k, v)| (*k, *v)).collect()
+                        engine.global_hotkeys.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
                     }
                 }
             },
@@ -88,25 +118,66 @@ impl Config {
                 let (font, index) = load_font(&engine.candidate_font);
                 (font, index)
             },
+            fallback_fonts: FallbackFonts::new(db),
+            app_profiles: engine
+                .app_profiles
+                .iter()
+                .map(|(app, profile)| {
+                    let profile_hangul_data = profile.hangul_layout.as_ref().map(|layout| {
+                        hangul_data(&HangulConfig {
+                            layout: layout.clone(),
+                            // Base layouts can still be switched by `CycleLayout`
+                            layouts: base_hangul
+                                .layout_names()
+                                .into_iter()
+                                .map(String::from)
+                                .collect(),
+                            word_commit: base_hangul.word_commit,
+                            preedit_johab: base_hangul.preedit_johab,
+                            ksx1001_only: base_hangul.ksx1001_only,
+                            capslock_behavior: base_hangul.capslock_behavior,
+                            addons: base_hangul.addons.clone(),
+                        })
+                    });
+
+                    (
+                        app.clone(),
+                        AppProfileData {
+                            default_category: profile.default_category,
+                            reset_on_focus: profile.reset_on_focus,
+                            hangul_data: profile_hangul_data,
+                            disabled_modes: profile.disabled_modes,
+                        },
+                    )
+                })
+                .collect(),
             preferred_direct: engine.latin.preferred_direct,
-            latin_data: LatinData::new(&engine.latin),
-            hangul_data,
+            latin_data,
+            hangul_data: hangul_data(&engine.hangul),
         }
     }
 
     pub fn new(engine: EngineConfig) -> Self {
-        let hangul_data = HangulData::new(
-            &engine.hangul,
-            kime_engine_backend_hangul::builtin_layouts(),
-        );
-
-        Self::new_impl(engine, hangul_data)
+        let latin_data =
+            LatinData::new(&engine.latin, kime_engine_backend_latin::builtin_layouts());
+        Self::new_impl(engine, latin_data, |hangul| {
+            HangulData::new(hangul, kime_engine_backend_hangul::builtin_layouts())
+        })
     }
 
     #[cfg(unix)]
     pub fn from_engine_config_with_dir(engine: EngineConfig, dir: &xdg::BaseDirectories) -> Self {
-        let hangul_data = HangulData::from_config_with_dir(&engine.hangul, dir);
-        Self::new_impl(engine, hangul_data)
+        let latin_data = LatinData::from_config_with_dir(&engine.latin, dir);
+        Self::new_impl(engine, latin_data, |hangul| {
+            HangulData::from_config_with_dir(hangul, dir)
+        })
+    }
+
+    /// Find app profile by window class or app id
+    pub fn find_app_profile(&self, app: &str) -> Option<usize> {
+        self.app_profiles
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case(app))
     }
 }
 
@@ -0,0 +1,133 @@
+use fontdb::{Database, ID};
+use std::sync::OnceLock;
+
+/// Unicode coverage of font face
+struct FaceCoverage {
+    id: ID,
+    /// Face has color bitmap (CBDT/sbix) which is preferred for emoji
+    color: bool,
+    /// Sorted inclusive ranges of codepoints which have glyph, built on first lookup
+    ranges: OnceLock<Vec<(u32, u32)>>,
+    /// Loaded on first use
+    data: OnceLock<Option<Vec<u8>>>,
+}
+
+impl FaceCoverage {
+    fn new(db: &Database, id: ID) -> Option<Self> {
+        let color = db.with_face_data(id, |data, index| {
+            let face = ttf_parser::Face::parse(data, index).ok()?;
+            Some(face.tables().cbdt.is_some() || face.tables().sbix.is_some())
+        })??;
+
+        Some(Self {
+            id,
+            color,
+            ranges: OnceLock::new(),
+            data: OnceLock::new(),
+        })
+    }
+
+    fn ranges(&self, db: &Database) -> &[(u32, u32)] {
+        self.ranges.get_or_init(|| {
+            let mut codepoints = db
+                .with_face_data(self.id, |data, index| {
+                    let face = ttf_parser::Face::parse(data, index).ok()?;
+                    let mut codepoints = Vec::new();
+
+                    for subtable in face.tables().cmap?.subtables {
+                        if subtable.is_unicode() {
+                            subtable.codepoints(|c| {
+                                if subtable.glyph_index(c).is_some_and(|id| id.0 != 0) {
+                                    codepoints.push(c);
+                                }
+                            });
+                        }
+                    }
+
+                    Some(codepoints)
+                })
+                .flatten()
+                .unwrap_or_default();
+
+            codepoints.sort_unstable();
+            codepoints.dedup();
+
+            let mut ranges: Vec<(u32, u32)> = Vec::new();
+
+            for c in codepoints {
+                match ranges.last_mut() {
+                    Some((_, end)) if *end + 1 == c => *end = c,
+                    _ => ranges.push((c, c)),
+                }
+            }
+
+            ranges
+        })
+    }
+
+    fn contains(&self, db: &Database, c: u32) -> bool {
+        self.ranges(db)
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+/// Emoji blocks which should be drawn by color font if there is
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x2300..=0x23FF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x1F000..=0x1FAFF
+    )
+}
+
+/// System fonts which are used when glyph is missing in configured font
+pub struct FallbackFonts {
+    db: Database,
+    /// Coverage of each face in `db` which is loaded on first visit, lookup stops at first face
+    /// which has glyph so most faces are never parsed
+    coverages: Vec<OnceLock<Option<FaceCoverage>>>,
+}
+
+impl FallbackFonts {
+    pub fn new(db: Database) -> Self {
+        let coverages = db.faces().iter().map(|_| OnceLock::new()).collect();
+
+        Self { db, coverages }
+    }
+
+    fn coverage(&self, index: usize) -> Option<&FaceCoverage> {
+        self.coverages[index]
+            .get_or_init(|| FaceCoverage::new(&self.db, self.db.faces()[index].id))
+            .as_ref()
+    }
+
+    /// Find font data and face index which has glyph of `c`
+    pub fn find(&self, c: char) -> Option<(&[u8], u32)> {
+        let c32 = c as u32;
+        let faces = || (0..self.coverages.len()).filter_map(|index| self.coverage(index));
+
+        // Monochrome faces like Symbola can come before color emoji font
+        let coverage = if is_emoji(c) {
+            faces().find(|coverage| coverage.color && coverage.contains(&self.db, c32))
+        } else {
+            None
+        }
+        .or_else(|| faces().find(|coverage| coverage.contains(&self.db, c32)))?;
+
+        let data = coverage.data.get_or_init(|| {
+            self.db
+                .with_face_data(coverage.id, |data, _index| data.to_vec())
+        });
+        let index = self.db.face(coverage.id)?.index;
+
+        data.as_deref().map(|data| (data, index))
+    }
+}
@@ -1,16 +1,23 @@
 mod config;
+mod font;
 mod os;
 
 #[cfg(unix)]
 pub use config::{load_engine_config_from_config_dir, load_other_configs_from_config_dir};
 pub use config::{
-    Config, DaemonConfig, DaemonModule, EngineConfig, EnumSet, Hotkey, IconColor, IndicatorConfig,
-    InputCategory, InputMode, LevelFilter, LogConfig, RawConfig,
+    AppProfile, Config, DaemonConfig, DaemonModule, EngineConfig, EnumSet, FocusOutBehavior,
+    HangulData, Hotkey, HotkeyBehavior, HotkeyResult, HotkeyTrigger, IconColor, IndicatorConfig,
+    InputCategory, InputMode, KeyMatching, LevelFilter, LogConfig, RawConfig, Translation,
 };
-pub use kime_engine_backend::{InputResult, Key, KeyCode, KeyMap, ModifierState};
+pub use font::FallbackFonts;
+pub use kime_engine_backend::{CapsLockBehavior, InputResult, Key, KeyCode, KeyMap, ModifierState};
+#[cfg(feature = "hanja")]
+pub use kime_engine_backend_hanja::CANDIDATE_PAGE_SIZE;
 
-use config::{HotkeyBehavior, HotkeyResult};
+use config::AppProfileData;
 use os::{DefaultOsContext, OsContext};
+use std::collections::BTreeMap;
+use std::time::Instant;
 
 use kime_engine_backend::{InputEngineBackend, InputEngineMode, InputEngineModeResult};
 #[cfg(feature = "emoji")]
@@ -22,10 +29,39 @@ use kime_engine_backend_latin::LatinEngine;
 #[cfg(feature = "math")]
 use kime_engine_backend_math::MathMode;
 
+/// Read current category and hangul layout from indicator
+pub fn read_layout_state() -> std::io::Result<(InputCategory, String)> {
+    DefaultOsContext::default().read_layout_state()
+}
+
+fn without_locks(key: Key) -> Key {
+    Key::new(key.code, key.state - ModifierState::locks())
+}
+
 pub struct InputEngine {
     engine_impl: EngineImpl,
     commit_buf: String,
     preedit_buf: String,
+    /// Focused app which has profile
+    app: Option<String>,
+    /// Last category of apps which have profile
+    app_categories: BTreeMap<String, InputCategory>,
+    /// Category before last switch for `Previous` hotkey
+    previous_category: Option<InputCategory>,
+    /// Last pressed key which can trigger `Tap` hotkey on release
+    tap_key: Option<Key>,
+    /// Keys which are held until `Sequence` or `Chord` hotkey is completed
+    held_keys: Vec<Key>,
+    /// Count of held keys including modifier keys between them
+    held_count: usize,
+    /// When last held key is pressed
+    held_at: Instant,
+    /// First held key is still pressed so it can complete `Chord`
+    chord_held: bool,
+    /// Count of held keys which are processed again by frontend
+    replay_count: usize,
+    /// Name of current hangul layout for indicator
+    hangul_layout: String,
     os_ctx: DefaultOsContext,
 }
 
@@ -37,22 +73,47 @@ impl Default for InputEngine {
 
 impl InputEngine {
     pub fn new(config: &Config) -> Self {
+        let engine_impl = EngineImpl::new(config);
+        let hangul_layout = engine_impl.hangul_layout(config).into();
+
         Self {
-            engine_impl: EngineImpl::new(config),
+            engine_impl,
             commit_buf: String::with_capacity(16),
             preedit_buf: String::with_capacity(16),
+            app: None,
+            app_categories: BTreeMap::new(),
+            previous_category: None,
+            tap_key: None,
+            held_keys: Vec::new(),
+            held_count: 0,
+            held_at: Instant::now(),
+            chord_held: false,
+            replay_count: 0,
+            hangul_layout,
             os_ctx: DefaultOsContext::default(),
         }
     }
 
     pub fn set_input_category(&mut self, category: InputCategory) {
+        if category != self.category() {
+            self.previous_category = Some(self.category());
+        }
+
         // Reset previous engine
         self.engine_impl.clear_preedit(&mut self.commit_buf);
         self.engine_impl.mode = None;
         self.engine_impl.category = category;
     }
 
-    pub fn set_input_mode(&mut self, mode: InputMode) -> bool {
+    pub fn set_input_mode(&mut self, mode: InputMode, config: &Config) -> bool {
+        if self
+            .engine_impl
+            .profile(config)
+            .is_some_and(|profile| profile.disabled_modes.contains(mode))
+        {
+            return false;
+        }
+
         self.engine_impl
             .set_mode(mode, &mut self.preedit_buf, &mut self.commit_buf)
     }
@@ -61,8 +122,98 @@ impl InputEngine {
         self.engine_impl.category
     }
 
+    pub fn input_mode(&self) -> Option<InputMode> {
+        self.engine_impl.mode
+    }
+
+    /// Let frontend draw candidate list instead of spawning candidate window
+    pub fn set_frontend_candidate(&mut self, frontend_candidate: bool) {
+        #[cfg(feature = "hanja")]
+        self.engine_impl
+            .hanja_mode
+            .set_frontend_candidate(frontend_candidate);
+        #[cfg(not(feature = "hanja"))]
+        let _ = frontend_candidate;
+    }
+
+    /// Candidate entries and selected index
+    pub fn candidates(&self) -> Option<(&'static [(&'static str, &'static str)], usize)> {
+        self.engine_impl.candidates()
+    }
+
+    /// Apply profile of focused app which is matched by window class or app id
+    pub fn set_app(&mut self, app: &str, config: &Config) -> InputResult {
+        if let Some(prev) = self.app.take() {
+            self.app_categories.insert(prev, self.category());
+        }
+
+        let profile = config.find_app_profile(app);
+
+        // Kept preedit can't be composed with another layout, focus already moved to new app
+        // so drop it instead of committing to wrong app
+        if self.engine_impl.profile != profile {
+            self.remove_preedit();
+            self.engine_impl.profile = profile;
+            self.update_hangul_layout(config);
+        }
+
+        let mut ret = InputResult::empty();
+
+        if let Some((name, profile)) = profile.and_then(|index| config.app_profiles.get(index)) {
+            let category = if profile.reset_on_focus {
+                profile.default_category.unwrap_or(config.default_category)
+            } else {
+                self.app_categories
+                    .get(name)
+                    .copied()
+                    .or(profile.default_category)
+                    .unwrap_or_else(|| self.category())
+            };
+
+            if category != self.category() {
+                self.set_input_category(category);
+                ret |= InputResult::LANGUAGE_CHANGED;
+            }
+
+            self.app = Some(name.clone());
+        }
+
+        ret | self.current_result()
+    }
+
+    /// Handle focus in, preedit which is kept by `on_focus_out` should be shown again
+    pub fn focus_in(&mut self) -> InputResult {
+        self.current_result()
+    }
+
+    /// Handle focus out with `on_focus_out` config
+    pub fn focus_out(&mut self, config: &Config) -> InputResult {
+        self.tap_key = None;
+        self.clear_held_keys();
+
+        match config.on_focus_out {
+            FocusOutBehavior::Commit => self.clear_preedit(),
+            FocusOutBehavior::Discard => self.remove_preedit(),
+            FocusOutBehavior::Keep => {}
+        }
+
+        self.current_result()
+    }
+
     pub fn update_layout_state(&mut self) -> std::io::Result<()> {
-        self.os_ctx.update_layout_state(self.category())
+        self.os_ctx
+            .update_layout_state(self.category(), &self.hangul_layout)
+    }
+
+    /// Name of current hangul layout
+    pub fn hangul_layout(&self) -> &str {
+        &self.hangul_layout
+    }
+
+    fn update_hangul_layout(&mut self, config: &Config) {
+        self.hangul_layout.clear();
+        self.hangul_layout
+            .push_str(self.engine_impl.hangul_layout(config));
     }
 
     fn try_get_global_input_category_state(&mut self, config: &Config) {
@@ -78,26 +229,169 @@ impl InputEngine {
         }
     }
 
-    fn try_hotkey<'c>(&self, key: Key, config: &'c Config) -> Option<Hotkey> {
+    fn hotkeys<'c>(&self, config: &'c Config) -> &'c [(HotkeyTrigger, Hotkey)] {
         if let Some(mode) = self.engine_impl.mode {
-            config.mode_hotkeys[mode]
-                .iter()
-                .find_map(|(k, v)| if *k == key { Some(*v) } else { None })
+            &config.mode_hotkeys[mode]
         } else {
-            config.category_hotkeys[self.engine_impl.category]
-                .iter()
-                .find_map(|(k, v)| if *k == key { Some(*v) } else { None })
+            &config.category_hotkeys[self.engine_impl.category]
         }
     }
 
-    pub fn press_key(&mut self, key: Key, config: &Config) -> InputResult {
-        self.try_get_global_input_category_state(config);
+    fn try_hotkey<'c>(&self, trigger: &HotkeyTrigger, config: &'c Config) -> Option<&'c Hotkey> {
+        self.hotkeys(config)
+            .iter()
+            .find_map(|(k, v)| if k == trigger { Some(v) } else { None })
+    }
+
+    /// `Sequence` hotkey which is completed by held keys
+    fn try_sequence_hotkey<'c>(&self, config: &'c Config) -> Option<&'c Hotkey> {
+        self.hotkeys(config).iter().find_map(|(k, v)| match k {
+            HotkeyTrigger::Sequence(keys) if *keys == self.held_keys => Some(v),
+            _ => None,
+        })
+    }
+
+    /// Held keys can be completed to `Sequence` hotkey by more keys
+    fn is_sequence_prefix(&self, config: &Config) -> bool {
+        self.hotkeys(config).iter().any(|(k, _)| match k {
+            HotkeyTrigger::Sequence(keys) => {
+                keys.len() > self.held_keys.len() && keys.starts_with(&self.held_keys)
+            }
+            _ => false,
+        })
+    }
+
+    fn try_chord_hotkey<'c>(
+        &self,
+        first: Key,
+        second: Key,
+        config: &'c Config,
+    ) -> Option<&'c Hotkey> {
+        self.hotkeys(config).iter().find_map(|(k, v)| match k {
+            HotkeyTrigger::Chord(a, b)
+                if (*a == first && *b == second) || (*a == second && *b == first) =>
+            {
+                Some(v)
+            }
+            _ => None,
+        })
+    }
+
+    fn is_chord_key(&self, key: Key, config: &Config) -> bool {
+        self.hotkeys(config).iter().any(|(k, _)| match k {
+            HotkeyTrigger::Chord(a, b) => *a == key || *b == key,
+            _ => false,
+        })
+    }
 
+    /// Hold key which could be part of `Sequence` or `Chord` hotkey, `None` when key should be
+    /// processed as usual
+    fn hold_key(&mut self, key: Key, repeat: bool, config: &Config) -> Option<InputResult> {
+        let now = Instant::now();
+
+        if self.held_count == 0 {
+            if repeat {
+                return None;
+            }
+
+            self.held_keys.push(key);
+            self.chord_held = self.is_chord_key(key, config);
+
+            if !self.chord_held && !self.is_sequence_prefix(config) {
+                self.held_keys.clear();
+                return None;
+            }
+        } else {
+            if repeat || now.duration_since(self.held_at) > config.hotkey_timeout {
+                return Some(self.replay_held_keys());
+            }
+
+            if self.chord_held {
+                if let Some(hotkey) = self.try_chord_hotkey(self.held_keys[0], key, config) {
+                    self.clear_held_keys();
+                    return Some(self.run_hotkey(hotkey, config));
+                }
+            }
+
+            self.held_keys.push(key);
+
+            if let Some(hotkey) = self.try_sequence_hotkey(config) {
+                self.clear_held_keys();
+                return Some(self.run_hotkey(hotkey, config));
+            }
+
+            if self.is_sequence_prefix(config) {
+                self.chord_held = false;
+            } else {
+                self.held_keys.pop();
+
+                // Modifier keys between keys of sequence are held with them
+                if key.code.modifier().is_empty() {
+                    return Some(self.replay_held_keys());
+                }
+            }
+        }
+
+        self.held_count += 1;
+        self.held_at = now;
+
+        Some(InputResult::CONSUMED | InputResult::HOLD)
+    }
+
+    /// Frontend will process held keys again, they are processed as usual this time
+    fn replay_held_keys(&mut self) -> InputResult {
+        self.replay_count = self.held_count;
+        self.held_keys.clear();
+        self.held_count = 0;
+        self.chord_held = false;
+
+        InputResult::CONSUMED | InputResult::REPLAY
+    }
+
+    /// When held keys should be processed again without completing hotkey, frontend should call
+    /// [`Self::flush_held_keys`] at this time
+    pub fn held_deadline(&self, config: &Config) -> Option<Instant> {
+        if self.held_count > 0 {
+            Some(self.held_at + config.hotkey_timeout)
+        } else {
+            None
+        }
+    }
+
+    /// Hotkey is not completed in time, result has `REPLAY` when there are held keys
+    pub fn flush_held_keys(&mut self) -> InputResult {
+        if self.held_count == 0 {
+            return self.current_result();
+        }
+
+        (self.replay_held_keys() - InputResult::CONSUMED) | self.current_result()
+    }
+
+    fn clear_held_keys(&mut self) {
+        self.held_keys.clear();
+        self.held_count = 0;
+        self.chord_held = false;
+        self.replay_count = 0;
+    }
+
+    /// Run hotkey, result has `CONSUMED` when hotkey consumes key
+    fn run_hotkey(&mut self, hotkey: &Hotkey, config: &Config) -> InputResult {
+        self.run_behaviors(hotkey.behaviors(), hotkey.result(), config)
+    }
+
+    /// Run behaviors in order, hotkey is processed when any behavior is processed
+    fn run_behaviors(
+        &mut self,
+        behaviors: &[HotkeyBehavior],
+        result: HotkeyResult,
+        config: &Config,
+    ) -> InputResult {
         let mut ret = InputResult::empty();
+        let mut processed = false;
+        let mut bypass = false;
 
-        if let Some(hotkey) = self.try_hotkey(key, config) {
-            let mut processed = false;
-            match hotkey.behavior() {
+        for behavior in behaviors.iter().copied() {
+            match behavior {
                 HotkeyBehavior::Switch(category) => {
                     if self.category() != category || self.engine_impl.mode.is_some() {
                         self.set_input_category(category);
@@ -119,7 +413,7 @@ impl InputEngine {
                     processed = true;
                 }
                 HotkeyBehavior::Mode(mode) => {
-                    processed = self.set_input_mode(mode);
+                    processed |= self.set_input_mode(mode, config);
                 }
                 HotkeyBehavior::Commit => {
                     if self.engine_impl.has_preedit() {
@@ -130,24 +424,109 @@ impl InputEngine {
                 HotkeyBehavior::Ignore => {
                     processed = true;
                 }
+                HotkeyBehavior::Previous => {
+                    if let Some(previous) = self.previous_category {
+                        if self.category() != previous || self.engine_impl.mode.is_some() {
+                            self.set_input_category(previous);
+                            ret |= InputResult::LANGUAGE_CHANGED;
+                            processed = true;
+                        }
+                    }
+                }
+                HotkeyBehavior::CycleLayout => {
+                    if self.engine_impl.cycle_layout(config, &mut self.commit_buf) {
+                        self.update_hangul_layout(config);
+                        ret |= InputResult::LANGUAGE_CHANGED;
+                        processed = true;
+                    }
+                }
+                HotkeyBehavior::ToggleAddon(addon) => {
+                    self.engine_impl.hangul_engine.toggle_addon(addon);
+                    processed = true;
+                }
+                HotkeyBehavior::Reset => {
+                    if self.engine_impl.has_preedit() {
+                        self.engine_impl.reset();
+                        processed = true;
+                    }
+                }
+                HotkeyBehavior::CommitAndBypass => {
+                    self.engine_impl.clear_preedit(&mut self.commit_buf);
+                    processed = true;
+                    bypass = true;
+                }
             }
+        }
 
-            match (hotkey.result(), processed) {
-                (HotkeyResult::Bypass, _) | (HotkeyResult::ConsumeIfProcessed, false) => {}
-                (HotkeyResult::Consume, _) | (HotkeyResult::ConsumeIfProcessed, true) => {
-                    ret |= InputResult::CONSUMED;
-                }
+        match (result, processed) {
+            _ if bypass => {}
+            (HotkeyResult::Bypass, _) | (HotkeyResult::ConsumeIfProcessed, false) => {}
+            (HotkeyResult::Consume, _) | (HotkeyResult::ConsumeIfProcessed, true) => {
+                ret |= InputResult::CONSUMED;
             }
-        } else if self
-            .engine_impl
-            .press_key(config, key, &mut self.commit_buf)
+        }
+
+        ret
+    }
+
+    /// Press key, lock states of key are only used by layouts
+    pub fn press_key(&mut self, key: Key, config: &Config) -> InputResult {
+        self.try_get_global_input_category_state(config);
+
+        // Held key which is processed again by frontend, it's already released
+        let replay = self.replay_count > 0;
+        let tap_key = self.tap_key;
+        let mut repeat = false;
+
+        if replay {
+            self.replay_count -= 1;
+        } else {
+            // Key is pressed again without release
+            repeat = tap_key.is_some_and(|tap| tap.code == key.code);
+            // Frontends may or may not set modifier of key itself
+            self.tap_key = Some(Key::new(
+                key.code,
+                without_locks(key).state - key.code.modifier(),
+            ));
+        }
+
+        let mut ret = InputResult::empty();
+
+        // Candidate list owns every key while it's shown
+        if self.engine_impl.candidates().is_some()
+            && self
+                .engine_impl
+                .press_key(config, key, &mut self.commit_buf)
         {
-            ret |= InputResult::CONSUMED;
-        } else if key.code == KeyCode::Shift {
-            // ignore shift key
+            return InputResult::CONSUMED | self.current_result();
+        }
+
+        let mut key = key;
+
+        if config.translate_hotkeys {
+            match self.translate_key(key, config) {
+                Ok(translated) => key = translated,
+                Err(ret) => return ret | self.current_result(),
+            }
+        }
+
+        let hotkey_key = without_locks(key);
+
+        if !replay {
+            if let Some(ret) = self.hold_key(hotkey_key, repeat, config) {
+                if ret.contains(InputResult::REPLAY) {
+                    // Frontend will press this key again after held keys
+                    self.tap_key = tap_key;
+                }
+
+                return ret | self.current_result();
+            }
+        }
+
+        if let Some(hotkey) = self.try_hotkey(&hotkey_key.into(), config) {
+            ret |= self.run_hotkey(hotkey, config);
         } else {
-            // clear preedit when get unhandled key
-            self.clear_preedit();
+            ret |= self.press_engine_key(key, config);
         }
 
         ret |= self.current_result();
@@ -155,13 +534,70 @@ impl InputEngine {
         ret
     }
 
+    /// Translation of key in current category
+    fn translation<'c>(&self, key: Key, config: &'c Config) -> Option<&'c Translation> {
+        config.translation_layers[self.category()]
+            .as_ref()?
+            .get_ref(without_locks(key))
+    }
+
+    /// Apply translation layer, `Err` is result of action which replaces key
+    fn translate_key(&mut self, key: Key, config: &Config) -> Result<Key, InputResult> {
+        match self.translation(key, config) {
+            None => Ok(key),
+            Some(Translation::Key(translated)) => Ok(Key::new(
+                translated.code,
+                translated.state | (key.state & ModifierState::locks()),
+            )),
+            Some(Translation::Hotkey(behaviors)) => {
+                Err(self.run_behaviors(behaviors, HotkeyResult::Consume, config))
+            }
+            Some(Translation::Commit(s)) => {
+                self.clear_preedit();
+                self.commit_buf.push_str(s);
+                Err(InputResult::CONSUMED)
+            }
+        }
+    }
+
+    /// Press key which isn't hotkey to engine
+    fn press_engine_key(&mut self, key: Key, config: &Config) -> InputResult {
+        let key = if config.translate_hotkeys {
+            key
+        } else {
+            match self.translate_key(key, config) {
+                Ok(key) => key,
+                Err(ret) => return ret,
+            }
+        };
+
+        if self
+            .engine_impl
+            .press_key(config, key, &mut self.commit_buf)
+        {
+            InputResult::CONSUMED
+        } else {
+            // Shift and AltGr only select level of next key e.g. for dead keys
+            if !key
+                .code
+                .modifier()
+                .intersects(ModifierState::SHIFT | ModifierState::ALTGR)
+            {
+                // clear preedit when get unhandled key
+                self.clear_preedit();
+            }
+            InputResult::empty()
+        }
+    }
+
     pub fn press_key_code(
         &mut self,
         hardware_code: u16,
         state: ModifierState,
-        numlock: bool,
         config: &Config,
     ) -> InputResult {
+        let numlock = state.contains(ModifierState::NUMLOCK);
+
         match KeyCode::from_hardware_code(hardware_code, numlock) {
             Some(code) => self.press_key(Key::new(code, state), config),
             None => {
@@ -171,6 +607,79 @@ impl InputEngine {
         }
     }
 
+    /// Press key from X keysym, see [`KeyCode::from_keysym`]
+    pub fn press_keysym(
+        &mut self,
+        keysym: u32,
+        state: ModifierState,
+        config: &Config,
+    ) -> InputResult {
+        match KeyCode::from_keysym(keysym) {
+            Some(code) => self.press_key(Key::new(code, state), config),
+            None => {
+                self.clear_preedit();
+                self.current_result()
+            }
+        }
+    }
+
+    /// Release key, `Tap` hotkey is triggered when no other key was pressed after it
+    ///
+    /// Release is never consumed so client can track state of keys
+    pub fn release_key(&mut self, key: Key, config: &Config) -> InputResult {
+        let mut ret = InputResult::empty();
+
+        // First key of chord is released alone, it can still be a part of sequence
+        if self.chord_held && self.held_keys[0].code == key.code {
+            self.chord_held = false;
+
+            if !self.is_sequence_prefix(config) {
+                return (self.replay_held_keys() - InputResult::CONSUMED) | self.current_result();
+            }
+        }
+
+        if let Some(tap) = self.tap_key.take().filter(|tap| tap.code == key.code) {
+            if let Some(hotkey) = self.try_hotkey(&HotkeyTrigger::Tap(tap), config) {
+                ret |= self.run_hotkey(hotkey, config) - InputResult::CONSUMED;
+            }
+        }
+
+        ret | self.current_result()
+    }
+
+    pub fn release_key_code(
+        &mut self,
+        hardware_code: u16,
+        state: ModifierState,
+        config: &Config,
+    ) -> InputResult {
+        let numlock = state.contains(ModifierState::NUMLOCK);
+
+        match KeyCode::from_hardware_code(hardware_code, numlock) {
+            Some(code) => self.release_key(Key::new(code, state), config),
+            None => {
+                self.tap_key = None;
+                self.current_result()
+            }
+        }
+    }
+
+    /// Release key from X keysym, see [`KeyCode::from_keysym`]
+    pub fn release_keysym(
+        &mut self,
+        keysym: u32,
+        state: ModifierState,
+        config: &Config,
+    ) -> InputResult {
+        match KeyCode::from_keysym(keysym) {
+            Some(code) => self.release_key(Key::new(code, state), config),
+            None => {
+                self.tap_key = None;
+                self.current_result()
+            }
+        }
+    }
+
     #[inline]
     pub fn clear_commit(&mut self) {
         self.commit_buf.clear();
@@ -192,6 +701,14 @@ impl InputEngine {
         &self.preedit_buf
     }
 
+    /// Byte offset of preedit string where composing part starts
+    ///
+    /// Part before it is already composed but not committed yet (e.g. `word_commit`)
+    #[inline]
+    pub fn preedit_composing_start(&self) -> usize {
+        self.engine_impl.preedit_composing_start()
+    }
+
     #[inline]
     pub fn commit_str(&self) -> &str {
         &self.commit_buf
@@ -201,6 +718,7 @@ impl InputEngine {
     pub fn reset(&mut self) {
         self.clear_commit();
         self.remove_preedit();
+        self.clear_held_keys();
     }
 
     #[inline]
@@ -225,6 +743,9 @@ impl InputEngine {
         if !self.engine_impl.check_ready() {
             ret |= InputResult::NOT_READY;
         }
+        if self.engine_impl.candidates().is_some() {
+            ret |= InputResult::HAS_CANDIDATE;
+        }
         ret
     }
 }
@@ -232,6 +753,8 @@ impl InputEngine {
 struct EngineImpl {
     category: InputCategory,
     mode: Option<InputMode>,
+    /// Index of app profile in config
+    profile: Option<usize>,
     latin_engine: LatinEngine,
     hangul_engine: HangulEngine,
     #[cfg(feature = "hanja")]
@@ -247,10 +770,12 @@ impl EngineImpl {
         Self {
             category: config.default_category,
             mode: None,
+            profile: None,
             latin_engine: LatinEngine::new(config.preferred_direct),
             hangul_engine: HangulEngine::new(
                 config.hangul_data.word_commit(),
                 config.hangul_data.preedit_johab(),
+                config.hangul_data.ksx1001_only(),
             ),
             #[cfg(feature = "hanja")]
             hanja_mode: HanjaMode::new(),
@@ -261,6 +786,12 @@ impl EngineImpl {
         }
     }
 
+    fn profile<'c>(&self, config: &'c Config) -> Option<&'c AppProfileData> {
+        self.profile
+            .and_then(|index| config.app_profiles.get(index))
+            .map(|(_, profile)| profile)
+    }
+
     pub fn set_mode(
         &mut self,
         mode: InputMode,
@@ -377,22 +908,58 @@ impl EngineImpl {
     pub fn end_ready(&mut self, commit_buf: &mut String) {
         do_mode!(@ret self, end_ready(commit_buf,));
     }
+
+    pub fn candidates(&self) -> Option<(&'static [(&'static str, &'static str)], usize)> {
+        match self.mode {
+            #[cfg(feature = "hanja")]
+            Some(InputMode::Hanja) => self.hanja_mode.candidates(),
+            _ => None,
+        }
+    }
+
+    pub fn preedit_composing_start(&self) -> usize {
+        match (self.mode, self.category) {
+            (None, InputCategory::Hangul) => self.hangul_engine.composing_start(),
+            _ => 0,
+        }
+    }
+
+    fn hangul_data<'c>(&self, config: &'c Config) -> &'c HangulData {
+        self.profile(config)
+            .and_then(|profile| profile.hangul_data.as_ref())
+            .unwrap_or(&config.hangul_data)
+    }
+
+    fn hangul_layout<'c>(&self, config: &'c Config) -> &'c str {
+        self.hangul_data(config)
+            .layout_name(self.hangul_engine.layout_index())
+    }
+
+    fn cycle_layout(&mut self, config: &Config, commit_buf: &mut String) -> bool {
+        let layout_count = self.hangul_data(config).layout_count();
+        self.hangul_engine.cycle_layout(layout_count, commit_buf)
+    }
+
+    /// Strip lock states and apply latin CapsLock behavior
+    fn split_locks(config: &Config, raw_key: Key) -> (Key, Key, bool) {
+        let capslock = raw_key.state.contains(ModifierState::CAPSLOCK);
+        let key = without_locks(raw_key);
+        let latin_key = config.latin_data.capslock_behavior().apply(key, capslock);
+
+        (key, latin_key, capslock)
+    }
 }
 
 impl InputEngineBackend for EngineImpl {
     type ConfigData = Config;
 
     fn press_key(&mut self, config: &Config, raw_key: Key, commit_buf: &mut String) -> bool {
-        let key = config
-            .translation_layer
-            .as_ref()
-            .and_then(|tl| tl.get(raw_key))
-            .unwrap_or(raw_key);
+        let (key, latin_key, capslock) = Self::split_locks(config, raw_key);
 
         match self.mode {
             #[cfg(feature = "emoji")]
             Some(InputMode::Emoji) => {
-                do_mode!(@retarm self, emoji_mode, press_key(&config.latin_data, key, commit_buf,))
+                do_mode!(@retarm self, emoji_mode, press_key(&config.latin_data, latin_key, commit_buf,))
             }
             #[cfg(feature = "hanja")]
             Some(InputMode::Hanja) => {
@@ -400,19 +967,20 @@ impl InputEngineBackend for EngineImpl {
             }
             #[cfg(feature = "math")]
             Some(InputMode::Math) => {
-                do_mode!(@retarm self, math_mode, press_key(&config.latin_data, key, commit_buf,))
+                do_mode!(@retarm self, math_mode, press_key(&config.latin_data, latin_key, commit_buf,))
             }
             _ => {}
         }
 
         match self.category {
             InputCategory::Hangul => {
-                self.hangul_engine
-                    .press_key(&config.hangul_data, key, commit_buf)
+                let hangul_data = self.hangul_data(config);
+                let key = hangul_data.capslock_behavior().apply(key, capslock);
+                self.hangul_engine.press_key(hangul_data, key, commit_buf)
             }
             InputCategory::Latin => {
                 self.latin_engine
-                    .press_key(&config.latin_data, key, commit_buf)
+                    .press_key(&config.latin_data, latin_key, commit_buf)
             }
         }
     }
@@ -2,8 +2,13 @@ use crate::InputCategory;
 use std::io;
 
 pub trait OsContext {
-    fn read_global_hangul_state(&mut self) -> io::Result<InputCategory>;
-    fn update_layout_state(&mut self, category: InputCategory) -> io::Result<()>;
+    /// Read category and hangul layout which are shown by indicator
+    fn read_layout_state(&mut self) -> io::Result<(InputCategory, String)>;
+    fn update_layout_state(&mut self, category: InputCategory, layout: &str) -> io::Result<()>;
+
+    fn read_global_hangul_state(&mut self) -> io::Result<InputCategory> {
+        self.read_layout_state().map(|(category, _)| category)
+    }
 }
 
 #[cfg(unix)]
@@ -33,20 +38,23 @@ mod unix {
         }
     }
 
+    // Message is category byte followed by utf8 name of hangul layout
     impl super::OsContext for OsContext {
-        fn read_global_hangul_state(&mut self) -> io::Result<InputCategory> {
-            let mut buf = [0; 1];
+        fn read_layout_state(&mut self) -> io::Result<(InputCategory, String)> {
+            let mut buf = Vec::new();
             let mut client = UnixStream::connect(&self.sock_path)?;
             client.set_read_timeout(Some(Duration::from_secs(2))).ok();
             client.set_write_timeout(Some(Duration::from_secs(2))).ok();
-            client.read_exact(&mut buf)?;
-            match buf[0] {
-                1 => Ok(InputCategory::Hangul),
-                _ => Ok(InputCategory::Latin),
-            }
+            client.read_to_end(&mut buf)?;
+            let category = match buf.first() {
+                Some(1) => InputCategory::Hangul,
+                Some(_) => InputCategory::Latin,
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+            Ok((category, String::from_utf8_lossy(&buf[1..]).into_owned()))
         }
 
-        fn update_layout_state(&mut self, category: InputCategory) -> io::Result<()> {
+        fn update_layout_state(&mut self, category: InputCategory, layout: &str) -> io::Result<()> {
             let category = match category {
                 InputCategory::Hangul => 1,
                 InputCategory::Latin => 0,
@@ -55,7 +63,8 @@ mod unix {
             let mut client = UnixStream::connect(&self.sock_path)?;
             client.set_read_timeout(Some(Duration::from_secs(2))).ok();
             client.set_write_timeout(Some(Duration::from_secs(2))).ok();
-            client.write_all(&[category])
+            client.write_all(&[category])?;
+            client.write_all(layout.as_bytes())
         }
     }
 }
@@ -68,11 +77,15 @@ mod fallback {
     pub struct OsContext;
 
     impl super::OsContext for OsContext {
-        fn read_global_hangul_state(&mut self) -> io::Result<InputCategory> {
+        fn read_layout_state(&mut self) -> io::Result<(InputCategory, String)> {
             Err(io::Error::new(io::ErrorKind::Other, "Unsupported platform"))
         }
 
-        fn update_layout_state(&mut self, _category: InputCategory) -> io::Result<()> {
+        fn update_layout_state(
+            &mut self,
+            _category: InputCategory,
+            _layout: &str,
+        ) -> io::Result<()> {
             Err(io::Error::new(io::ErrorKind::Other, "Unsupported platform"))
         }
     }
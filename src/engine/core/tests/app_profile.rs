@@ -0,0 +1,88 @@
+mod shared;
+
+use kime_engine_core::{
+    AppProfile, Config, EngineConfig, InputCategory, InputEngine, InputMode, InputResult, Key,
+    KeyCode,
+};
+use shared::test_engine;
+
+fn engine() -> (InputEngine, Config) {
+    let mut config = EngineConfig {
+        default_category: InputCategory::Hangul,
+        ..Default::default()
+    };
+    config.app_profiles.insert(
+        "Alacritty".into(),
+        AppProfile {
+            default_category: Some(InputCategory::Latin),
+            reset_on_focus: true,
+            disabled_modes: InputMode::Hanja.into(),
+            ..Default::default()
+        },
+    );
+    config.app_profiles.insert(
+        "telegram".into(),
+        AppProfile {
+            hangul_layout: Some("sebeolsik-3-90".into()),
+            ..Default::default()
+        },
+    );
+    test_engine(config, InputCategory::Hangul)
+}
+
+#[test]
+fn reset_on_focus() {
+    let (mut engine, config) = engine();
+
+    let ret = engine.set_app("alacritty", &config);
+    assert!(ret.contains(InputResult::LANGUAGE_CHANGED));
+    assert_eq!(engine.category(), InputCategory::Latin);
+
+    engine.set_input_category(InputCategory::Hangul);
+    engine.set_app("firefox", &config);
+    engine.set_app("Alacritty", &config);
+    assert_eq!(engine.category(), InputCategory::Latin);
+}
+
+#[test]
+fn remember_category() {
+    let (mut engine, config) = engine();
+
+    engine.set_app("telegram", &config);
+    assert_eq!(engine.category(), InputCategory::Hangul);
+
+    engine.set_app("Alacritty", &config);
+    assert_eq!(engine.category(), InputCategory::Latin);
+
+    engine.set_app("telegram", &config);
+    assert_eq!(engine.category(), InputCategory::Hangul);
+}
+
+#[test]
+fn hangul_layout() {
+    let (mut engine, config) = engine();
+
+    engine.set_app("telegram", &config);
+    engine.press_key(Key::normal(KeyCode::K), &config);
+    assert_eq!(engine.preedit_str(), "ㄱ");
+
+    engine.set_app("firefox", &config);
+    assert_eq!(engine.commit_str(), "");
+    assert_eq!(engine.preedit_str(), "");
+    engine.press_key(Key::normal(KeyCode::R), &config);
+    assert_eq!(engine.preedit_str(), "ㄱ");
+}
+
+#[test]
+fn disabled_modes() {
+    let (mut engine, config) = engine();
+
+    engine.set_app("Alacritty", &config);
+    engine.set_input_category(InputCategory::Hangul);
+    engine.press_key(Key::normal(KeyCode::R), &config);
+    engine.press_key(Key::normal(KeyCode::K), &config);
+
+    let ret = engine.press_key(Key::normal(KeyCode::F9), &config);
+    assert!(!ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.input_mode(), None);
+}
@@ -0,0 +1,155 @@
+use kime_engine_backend_latin::{load_layout, ComposeMatch, ComposeSym, ComposeTable, LatinData};
+use kime_engine_core::{
+    Config, EngineConfig, InputCategory, InputEngine, InputResult, Key, KeyCode::*,
+};
+
+const COMPOSE: &str = r#"
+# comment
+<dead_acute> <e>            : "é"   eacute # LATIN SMALL LETTER E WITH ACUTE
+<dead_acute> <space>        : "'"   apostrophe
+<Multi_key> <a> <e>         : "æ"   ae
+<Multi_key> <o> <c>         : "©"   copyright
+<Multi_key> <quotedbl> <u>  : "ü"
+<Multi_key> <less> <3>      : "\342\231\245"
+~Ctrl <a>                   : "ignored"
+"#;
+
+// Qwerty with dead acute on quote
+const LAYOUT: &str = r#"
+A: a
+C: c
+E: e
+O: o
+U: u
+3: "3"
+S-Quote: '"'
+S-Comma: <
+Space: ' '
+Quote: "́"
+"#;
+
+fn config() -> Config {
+    let mut engine = EngineConfig::default();
+    engine.latin.preferred_direct = false;
+    engine.latin.compose_key = Some(Key::normal(Menu));
+
+    let mut latin_data = LatinData::new(
+        &engine.latin,
+        std::iter::once(("Qwerty".into(), load_layout(LAYOUT).unwrap())),
+    );
+    let mut table = ComposeTable::default();
+    table.parse(COMPOSE);
+    latin_data.set_compose_table(table);
+
+    let mut config = Config::new(engine);
+    config.latin_data = latin_data;
+    config
+}
+
+#[track_caller]
+fn test_input(keys: &[(Key, &str, &str)]) {
+    let config = config();
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    for (key, preedit, commit) in keys.iter().copied() {
+        let ret = engine.press_key(key, &config);
+        assert!(ret.contains(InputResult::CONSUMED));
+        assert_eq!(engine.preedit_str(), preedit);
+        assert_eq!(engine.commit_str(), commit);
+        engine.clear_commit();
+    }
+}
+
+#[test]
+fn parse() {
+    let mut table = ComposeTable::default();
+    table.parse(COMPOSE);
+
+    let multi_less = [ComposeSym::Multi, ComposeSym::Char('<')];
+    assert_eq!(table.lookup(&multi_less), ComposeMatch::Prefix);
+    assert_eq!(
+        table.lookup(&[multi_less[0], multi_less[1], ComposeSym::Char('3')]),
+        ComposeMatch::Exact("♥")
+    );
+    assert_eq!(table.lookup(&[ComposeSym::Char('a')]), ComposeMatch::None);
+}
+
+#[test]
+fn conflict() {
+    let mut table = ComposeTable::default();
+    let multi_a = [ComposeSym::Multi, ComposeSym::Char('a')];
+    let multi_a_e = [multi_a[0], multi_a[1], ComposeSym::Char('e')];
+
+    // Later definition replaces longer sequence
+    table.parse(r#"<Multi_key> <a> <e> : "æ""#);
+    table.parse(r#"<Multi_key> <a> : "a""#);
+    assert_eq!(table.lookup(&multi_a), ComposeMatch::Exact("a"));
+    assert_eq!(table.lookup(&multi_a_e), ComposeMatch::None);
+
+    // Later definition replaces prefix
+    table.parse(r#"<Multi_key> <a> <e> : "æ""#);
+    assert_eq!(table.lookup(&multi_a), ComposeMatch::Prefix);
+    assert_eq!(table.lookup(&multi_a_e), ComposeMatch::Exact("æ"));
+}
+
+#[test]
+fn dead_key() {
+    test_input(&[
+        (Key::normal(Quote), "◌\u{0301}", ""),
+        (Key::normal(E), "", "é"),
+        (Key::normal(Quote), "◌\u{0301}", ""),
+        (Key::normal(Space), "", "'"),
+    ]);
+}
+
+#[test]
+fn dead_key_unmatched() {
+    test_input(&[
+        (Key::normal(Quote), "◌\u{0301}", ""),
+        (Key::normal(C), "", "c"),
+    ]);
+}
+
+#[test]
+fn compose_key() {
+    test_input(&[
+        (Key::normal(Menu), "⎄", ""),
+        (Key::normal(A), "⎄a", ""),
+        (Key::normal(E), "", "æ"),
+        (Key::normal(Menu), "⎄", ""),
+        (Key::shift(Quote), "⎄\"", ""),
+        (Key::normal(U), "", "ü"),
+        (Key::normal(Menu), "⎄", ""),
+        (Key::shift(Comma), "⎄<", ""),
+        (Key::normal(Three), "", "♥"),
+    ]);
+}
+
+#[test]
+fn backspace() {
+    test_input(&[
+        (Key::normal(Menu), "⎄", ""),
+        (Key::normal(O), "⎄o", ""),
+        (Key::normal(Backspace), "⎄", ""),
+        (Key::normal(A), "⎄a", ""),
+        (Key::normal(E), "", "æ"),
+    ]);
+}
+
+#[test]
+fn without_compose_table() {
+    let mut engine = EngineConfig::default();
+    engine.latin.preferred_direct = false;
+    let mut config = Config::new(engine);
+    config.latin_data = LatinData::new(
+        &Default::default(),
+        std::iter::once(("Qwerty".into(), load_layout(LAYOUT).unwrap())),
+    );
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    engine.press_key(Key::normal(Quote), &config);
+    assert_eq!(engine.preedit_str(), "");
+    assert_eq!(engine.commit_str(), "\u{0301}");
+}
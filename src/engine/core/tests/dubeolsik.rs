@@ -43,6 +43,59 @@ fn word_hello() {
     ])
 }
 
+#[test]
+fn word_composing_start() {
+    use kime_engine_core::{Config, InputEngine};
+
+    let mut config = default_config();
+    config.hangul.word_commit = true;
+    let config = Config::new(config);
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Hangul);
+
+    for key in [D, K, S, S, U] {
+        engine.press_key(Key::normal(key), &config);
+    }
+
+    assert_eq!(engine.preedit_str(), "안녀");
+    assert_eq!(engine.preedit_composing_start(), "안".len());
+}
+
+#[test]
+fn ksx1001_only() {
+    let mut config = default_config();
+    config.hangul.ksx1001_only = true;
+
+    test_input_impl(
+        config,
+        InputCategory::Hangul,
+        &[
+            (Key::shift(E), "ㄸ", ""),
+            (Key::normal(H), "또", ""),
+            (Key::normal(A), "ㄸㅗㅁ", ""),
+            (Key::shift(One), "", "ㄸㅗㅁ!"),
+            (Key::normal(R), "ㄱ", ""),
+            (Key::normal(K), "가", ""),
+            (Key::normal(S), "간", ""),
+            (Key::shift(One), "", "간!"),
+        ],
+    );
+}
+
+#[test]
+fn keysym() {
+    use kime_engine_core::{Config, InputEngine, ModifierState};
+
+    let config = Config::new(default_config());
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Hangul);
+
+    // r, shift + O
+    engine.press_keysym(0x72, ModifierState::empty(), &config);
+    engine.press_keysym(0x4F, ModifierState::SHIFT, &config);
+    assert_eq!(engine.preedit_str(), "걔");
+}
+
 #[test]
 fn esc() {
     test_input(&[
@@ -76,10 +129,11 @@ fn arrow() {
 fn shift_bypass() {
     test_input(&[
         (Key::normal(R), "ㄱ", ""),
-        (Key::normal(Shift), "ㄱ", "PASS"),
-        (Key::shift(Shift), "ㄱ", "PASS"),
-        (Key::super_(Shift), "ㄱ", "PASS"),
-        (Key::alt(Shift), "ㄱ", "PASS"),
+        (Key::normal(ShiftL), "ㄱ", "PASS"),
+        (Key::shift(ShiftL), "ㄱ", "PASS"),
+        (Key::super_(ShiftL), "ㄱ", "PASS"),
+        (Key::alt(ShiftL), "ㄱ", "PASS"),
+        (Key::shift(ShiftR), "ㄱ", "PASS"),
         (Key::shift(O), "걔", ""),
     ])
 }
@@ -204,3 +258,21 @@ fn backspace_moum_compose() {
         (Key::normal(Backspace), "ㅇ", ""),
     ])
 }
+
+#[test]
+fn capslock() {
+    use kime_engine_core::{CapsLockBehavior, ModifierState};
+
+    let capslock = |code| Key::new(code, ModifierState::CAPSLOCK);
+
+    test_input(&[(capslock(E), "ㄷ", ""), (capslock(K), "다", "")]);
+
+    let mut config = default_config();
+    config.hangul.capslock_behavior = CapsLockBehavior::Shift;
+
+    test_input_impl(
+        config,
+        InputCategory::Hangul,
+        &[(capslock(E), "ㄸ", ""), (capslock(K), "따", "")],
+    );
+}
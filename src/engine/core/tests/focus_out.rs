@@ -0,0 +1,70 @@
+mod shared;
+
+use kime_engine_core::{
+    EngineConfig, FocusOutBehavior, InputCategory, InputEngine, InputResult, Key, KeyCode,
+};
+use shared::test_engine;
+
+fn focus_out(on_focus_out: FocusOutBehavior) -> (InputEngine, InputResult) {
+    let (mut engine, config) = test_engine(
+        EngineConfig {
+            on_focus_out,
+            ..Default::default()
+        },
+        InputCategory::Hangul,
+    );
+    engine.press_key(Key::normal(KeyCode::R), &config);
+    engine.press_key(Key::normal(KeyCode::K), &config);
+
+    let ret = engine.focus_out(&config);
+    (engine, ret)
+}
+
+#[test]
+fn commit() {
+    let (mut engine, ret) = focus_out(FocusOutBehavior::Commit);
+    assert!(ret.contains(InputResult::HAS_COMMIT));
+    assert!(!ret.contains(InputResult::HAS_PREEDIT));
+    assert_eq!(engine.commit_str(), "가");
+    assert_eq!(engine.preedit_str(), "");
+}
+
+#[test]
+fn discard() {
+    let (mut engine, ret) = focus_out(FocusOutBehavior::Discard);
+    assert!(!ret.intersects(InputResult::HAS_COMMIT | InputResult::HAS_PREEDIT));
+    assert_eq!(engine.commit_str(), "");
+    assert_eq!(engine.preedit_str(), "");
+}
+
+#[test]
+fn keep() {
+    let (mut engine, ret) = focus_out(FocusOutBehavior::Keep);
+    assert!(!ret.contains(InputResult::HAS_COMMIT));
+    assert!(ret.contains(InputResult::HAS_PREEDIT));
+
+    let ret = engine.focus_in();
+    assert!(ret.contains(InputResult::HAS_PREEDIT));
+    assert_eq!(engine.preedit_str(), "가");
+}
+
+#[test]
+fn focus_in_after_commit() {
+    let (mut engine, _) = focus_out(FocusOutBehavior::Commit);
+    engine.clear_commit();
+
+    let ret = engine.focus_in();
+    assert!(!ret.intersects(InputResult::HAS_COMMIT | InputResult::HAS_PREEDIT));
+    assert_eq!(engine.preedit_str(), "");
+}
+
+#[test]
+fn focus_in_after_reset() {
+    // Frontend resets kept preedit when new content shouldn't be composed
+    let (mut engine, _) = focus_out(FocusOutBehavior::Keep);
+    engine.reset();
+
+    let ret = engine.focus_in();
+    assert!(!ret.intersects(InputResult::HAS_COMMIT | InputResult::HAS_PREEDIT));
+    assert_eq!(engine.preedit_str(), "");
+}
@@ -0,0 +1,63 @@
+use kime_engine_core::{
+    Config, EngineConfig, InputCategory, InputEngine, InputMode, InputResult, Key, KeyCode,
+};
+
+fn hanja_engine(config: &Config) -> InputEngine {
+    let mut engine = InputEngine::new(config);
+    engine.set_frontend_candidate(true);
+    engine.set_input_category(InputCategory::Hangul);
+
+    engine.press_key(Key::normal(KeyCode::R), config);
+    engine.press_key(Key::normal(KeyCode::K), config);
+    assert_eq!(engine.preedit_str(), "가");
+
+    let ret = engine.press_key(Key::normal(KeyCode::F9), config);
+    assert!(ret.contains(InputResult::HAS_CANDIDATE));
+    assert_eq!(engine.input_mode(), Some(InputMode::Hanja));
+    assert_eq!(engine.preedit_str(), "가");
+
+    engine
+}
+
+#[test]
+fn select_with_cursor() {
+    let config = Config::new(EngineConfig::default());
+    let mut engine = hanja_engine(&config);
+
+    let (entries, cursor) = engine.candidates().unwrap();
+    assert_eq!(cursor, 0);
+
+    let ret = engine.press_key(Key::normal(KeyCode::Down), &config);
+    assert!(ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.candidates().unwrap().1, 1);
+
+    let ret = engine.press_key(Key::normal(KeyCode::Enter), &config);
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::HAS_COMMIT));
+    assert!(!ret.contains(InputResult::HAS_CANDIDATE));
+    assert_eq!(engine.commit_str(), entries[1].0);
+    assert_eq!(engine.input_mode(), None);
+}
+
+#[test]
+fn select_with_number() {
+    let config = Config::new(EngineConfig::default());
+    let mut engine = hanja_engine(&config);
+
+    let (entries, _) = engine.candidates().unwrap();
+
+    let ret = engine.press_key(Key::normal(KeyCode::One), &config);
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::HAS_COMMIT));
+    assert_eq!(engine.commit_str(), entries[0].0);
+    assert!(engine.candidates().is_none());
+}
+
+#[test]
+fn cancel() {
+    let config = Config::new(EngineConfig::default());
+    let mut engine = hanja_engine(&config);
+
+    let ret = engine.press_key(Key::normal(KeyCode::Esc), &config);
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::HAS_COMMIT));
+    assert_eq!(engine.commit_str(), "가");
+    assert!(engine.candidates().is_none());
+}
@@ -0,0 +1,122 @@
+mod shared;
+
+use kime_engine_backend_hangul::Addon;
+use kime_engine_core::{
+    Config, Hotkey, HotkeyBehavior, HotkeyResult, InputCategory, InputEngine, InputResult, Key,
+    KeyCode::*,
+};
+use shared::{hotkey_config, test_engine};
+
+fn engine(hotkeys: &[(Key, Hotkey)]) -> (InputEngine, Config) {
+    test_engine(hotkey_config(hotkeys), InputCategory::Hangul)
+}
+
+#[test]
+fn parse() {
+    let hotkey: Hotkey = serde_yaml::from_str("behavior: Commit\nresult: Bypass").unwrap();
+    assert_eq!(hotkey.behaviors(), &[HotkeyBehavior::Commit]);
+
+    let hotkey: Hotkey =
+        serde_yaml::from_str("behavior: [Commit, !Switch Latin]\nresult: Consume").unwrap();
+    assert_eq!(
+        hotkey.behaviors(),
+        &[
+            HotkeyBehavior::Commit,
+            HotkeyBehavior::Switch(InputCategory::Latin)
+        ]
+    );
+
+    let hotkey: Hotkey =
+        serde_yaml::from_str("behavior: !ToggleAddon FlexibleComposeOrder\nresult: Consume")
+            .unwrap();
+    assert_eq!(
+        hotkey.behaviors(),
+        &[HotkeyBehavior::ToggleAddon(Addon::FlexibleComposeOrder)]
+    );
+}
+
+#[test]
+fn chain() {
+    let (mut engine, config) = engine(&[(
+        Key::normal(Esc),
+        Hotkey::chain(
+            vec![
+                HotkeyBehavior::Commit,
+                HotkeyBehavior::Switch(InputCategory::Latin),
+            ],
+            HotkeyResult::Bypass,
+        ),
+    )]);
+
+    engine.press_key(Key::normal(R), &config);
+    let ret = engine.press_key(Key::normal(Esc), &config);
+    assert!(ret.contains(InputResult::LANGUAGE_CHANGED));
+    assert!(!ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.commit_str(), "ㄱ");
+    assert_eq!(engine.category(), InputCategory::Latin);
+}
+
+#[test]
+fn previous() {
+    let (mut engine, config) = engine(&[(
+        Key::normal(F1),
+        Hotkey::new(HotkeyBehavior::Previous, HotkeyResult::Consume),
+    )]);
+
+    engine.set_input_category(InputCategory::Latin);
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.category(), InputCategory::Hangul);
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.category(), InputCategory::Latin);
+}
+
+#[test]
+fn toggle_addon() {
+    let (mut engine, config) = engine(&[(
+        Key::normal(F1),
+        Hotkey::new(
+            HotkeyBehavior::ToggleAddon(Addon::FlexibleComposeOrder),
+            HotkeyResult::Consume,
+        ),
+    )]);
+
+    engine.press_key(Key::normal(K), &config);
+    engine.press_key(Key::normal(R), &config);
+    assert_eq!(engine.preedit_str(), "ㄱ");
+    engine.reset();
+
+    engine.press_key(Key::normal(F1), &config);
+    engine.press_key(Key::normal(K), &config);
+    engine.press_key(Key::normal(R), &config);
+    assert_eq!(engine.preedit_str(), "가");
+}
+
+#[test]
+fn reset() {
+    let (mut engine, config) = engine(&[(
+        Key::normal(Esc),
+        Hotkey::new(HotkeyBehavior::Reset, HotkeyResult::ConsumeIfProcessed),
+    )]);
+
+    engine.press_key(Key::normal(R), &config);
+    let ret = engine.press_key(Key::normal(Esc), &config);
+    assert!(ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.preedit_str(), "");
+    assert_eq!(engine.commit_str(), "");
+
+    let ret = engine.press_key(Key::normal(Esc), &config);
+    assert!(!ret.contains(InputResult::CONSUMED));
+}
+
+#[test]
+fn commit_and_bypass() {
+    let (mut engine, config) = engine(&[(
+        Key::normal(Esc),
+        Hotkey::new(HotkeyBehavior::CommitAndBypass, HotkeyResult::Consume),
+    )]);
+
+    engine.press_key(Key::normal(R), &config);
+    let ret = engine.press_key(Key::normal(Esc), &config);
+    assert!(!ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.commit_str(), "ㄱ");
+}
@@ -1,7 +1,7 @@
 #[macro_use]
 mod shared;
 
-define_layout_test!("dubeolsik", LatinLayout::Qwerty, InputCategory::Latin);
+define_layout_test!("dubeolsik", "Qwerty", InputCategory::Latin);
 
 #[test]
 fn qwerty() {
@@ -11,3 +11,37 @@ fn qwerty() {
         (Key::shift(SemiColon), "", "PASS"),
     ]);
 }
+
+#[test]
+fn capslock() {
+    use kime_engine_core::{CapsLockBehavior, ModifierState};
+
+    let capslock = |code| Key::new(code, ModifierState::CAPSLOCK);
+
+    let mut config = default_config();
+    config.latin.preferred_direct = false;
+
+    test_input_impl(
+        config,
+        InputCategory::Latin,
+        &[
+            (capslock(A), "", "A"),
+            (capslock(One), "", "1"),
+            (
+                Key::new(A, ModifierState::CAPSLOCK | ModifierState::SHIFT),
+                "",
+                "a",
+            ),
+        ],
+    );
+
+    let mut config = default_config();
+    config.latin.preferred_direct = false;
+    config.latin.capslock_behavior = CapsLockBehavior::Shift;
+
+    test_input_impl(
+        config,
+        InputCategory::Latin,
+        &[(capslock(A), "", "A"), (capslock(One), "", "!")],
+    );
+}
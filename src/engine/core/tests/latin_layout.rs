@@ -0,0 +1,96 @@
+use kime_engine_backend_latin::{load_layout, LatinData};
+use kime_engine_core::{
+    Config, EngineConfig, InputCategory, InputEngine, Key, KeyCode::*, ModifierState,
+};
+
+const MATH: Key = Key::new(Backslash, ModifierState::CONTROL.union(ModifierState::ALT));
+
+// Part of workman layout
+const WORKMAN: &str = r#"
+Q: q
+W: d
+E: r
+R: w
+A: a
+S: s
+D: h
+F: t
+O: p
+SemiColon: i
+Backslash: \
+"#;
+
+fn config(layout: &str) -> Config {
+    let mut engine = EngineConfig::default();
+    engine.latin.preferred_direct = false;
+    engine.latin.layout = layout.into();
+
+    let latin_data = LatinData::new(
+        &engine.latin,
+        std::iter::once(("Workman".into(), load_layout(WORKMAN).unwrap()))
+            .chain(kime_engine_backend_latin::builtin_layouts()),
+    );
+    let mut config = Config::new(engine);
+    config.latin_data = latin_data;
+    config
+}
+
+#[test]
+fn custom_layout() {
+    let config = config("Workman");
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    engine.press_key(Key::normal(W), &config);
+    engine.press_key(Key::normal(D), &config);
+    assert_eq!(engine.commit_str(), "dh");
+}
+
+#[test]
+fn builtin_layout() {
+    let config = config("Colemak");
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    engine.press_key(Key::normal(S), &config);
+    assert_eq!(engine.commit_str(), "r");
+}
+
+#[test]
+fn dvorak_layout() {
+    let config = config("Dvorak");
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    for key in [
+        Key::normal(S),
+        Key::normal(W),
+        Key::new(Z, ModifierState::SHIFT),
+    ] {
+        engine.press_key(key, &config);
+    }
+    assert_eq!(engine.commit_str(), "o,:");
+}
+
+#[test]
+fn unknown_layout() {
+    let config = config("NotExists");
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    engine.press_key(Key::normal(W), &config);
+    assert_eq!(engine.commit_str(), "w");
+}
+
+#[test]
+fn math_query() {
+    let config = config("Workman");
+    let mut engine = InputEngine::new(&config);
+    engine.set_input_category(InputCategory::Latin);
+
+    engine.press_key(MATH, &config);
+    for key in [Backslash, O, SemiColon] {
+        engine.press_key(Key::normal(key), &config);
+    }
+    assert_eq!(engine.preedit_str(), "\\pi");
+}
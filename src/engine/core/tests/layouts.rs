@@ -0,0 +1,98 @@
+mod shared;
+
+use kime_engine_core::{
+    AppProfile, Config, EngineConfig, Hotkey, HotkeyBehavior, HotkeyResult, InputCategory,
+    InputEngine, InputResult, Key, KeyCode::*,
+};
+use shared::{hotkey_config, test_engine};
+
+fn layouts_config(layouts: &[&str]) -> EngineConfig {
+    let mut config = hotkey_config(&[(
+        Key::normal(F1),
+        Hotkey::new(HotkeyBehavior::CycleLayout, HotkeyResult::Consume),
+    )]);
+    config.hangul.layouts = layouts.iter().map(|l| l.to_string()).collect();
+    config
+}
+
+fn engine(layouts: &[&str]) -> (InputEngine, Config) {
+    test_engine(layouts_config(layouts), InputCategory::Hangul)
+}
+
+#[test]
+fn loaded_layouts() {
+    let (_, config) = engine(&["sebeolsik-3-90", "dubeolsik", "not-exists"]);
+
+    assert_eq!(
+        config.hangul_data.layout_names().collect::<Vec<_>>(),
+        ["dubeolsik", "sebeolsik-3-90"]
+    );
+}
+
+#[test]
+fn cycle_layout() {
+    let (mut engine, config) = engine(&["sebeolsik-3-90"]);
+    assert_eq!(engine.hangul_layout(), "dubeolsik");
+
+    engine.press_key(Key::normal(R), &config);
+    assert_eq!(engine.preedit_str(), "ㄱ");
+
+    let ret = engine.press_key(Key::normal(F1), &config);
+    assert!(ret.contains(InputResult::LANGUAGE_CHANGED | InputResult::CONSUMED));
+    assert_eq!(engine.commit_str(), "ㄱ");
+    assert_eq!(engine.hangul_layout(), "sebeolsik-3-90");
+    engine.clear_commit();
+
+    engine.press_key(Key::normal(J), &config);
+    engine.press_key(Key::normal(F), &config);
+    assert_eq!(engine.preedit_str(), "아");
+    engine.reset();
+
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.hangul_layout(), "dubeolsik");
+}
+
+#[test]
+fn single_layout() {
+    let (mut engine, config) = engine(&[]);
+
+    let ret = engine.press_key(Key::normal(F1), &config);
+    assert!(!ret.contains(InputResult::LANGUAGE_CHANGED));
+    assert_eq!(engine.hangul_layout(), "dubeolsik");
+}
+
+#[test]
+fn layout_addons() {
+    let (mut engine, config) = engine(&["sebeolsik-3-90"]);
+
+    // `TreatJongseongAsChoseong` is only enabled for dubeolsik
+    for key in [D, K, S, K] {
+        engine.press_key(Key::normal(key), &config);
+    }
+    assert_eq!(engine.commit_str(), "아");
+    assert_eq!(engine.preedit_str(), "나");
+}
+
+#[test]
+fn app_profile_layouts() {
+    let mut config = layouts_config(&["sebeolsik-3-90"]);
+    config.app_profiles.insert(
+        "telegram".into(),
+        AppProfile {
+            hangul_layout: Some("sebeolsik-3-91".into()),
+            ..Default::default()
+        },
+    );
+    let (mut engine, config) = test_engine(config, InputCategory::Hangul);
+
+    engine.set_app("telegram", &config);
+    assert_eq!(engine.hangul_layout(), "sebeolsik-3-91");
+
+    // Profile layout comes first then layouts of base config
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.hangul_layout(), "dubeolsik");
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.hangul_layout(), "sebeolsik-3-90");
+    engine.press_key(Key::normal(F1), &config);
+    assert_eq!(engine.hangul_layout(), "sebeolsik-3-91");
+}
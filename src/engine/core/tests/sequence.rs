@@ -0,0 +1,286 @@
+mod shared;
+
+use kime_engine_core::{
+    Config, EngineConfig, Hotkey, HotkeyBehavior, HotkeyResult, HotkeyTrigger, InputCategory,
+    InputEngine, InputMode, InputResult, Key, KeyCode::*,
+};
+use shared::{hotkey_config, test_engine};
+use std::time::Instant;
+
+/// Process keys like frontends do, held keys are kept until next result
+struct Frontend {
+    engine: InputEngine,
+    config: Config,
+    held: Vec<Key>,
+    /// Keys which are sent to client
+    bypassed: Vec<Key>,
+}
+
+impl Frontend {
+    fn new(triggers: &[&str], hotkey_timeout: u64) -> Self {
+        let hotkeys: Vec<(HotkeyTrigger, _)> = triggers
+            .iter()
+            .map(|trigger| {
+                (
+                    trigger.parse().unwrap(),
+                    Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+                )
+            })
+            .collect();
+
+        Self::with_config(EngineConfig {
+            hotkey_timeout,
+            ..hotkey_config(&hotkeys)
+        })
+    }
+
+    fn with_config(config: EngineConfig) -> Self {
+        let (engine, config) = test_engine(config, InputCategory::Latin);
+
+        Self {
+            engine,
+            config,
+            held: Vec::new(),
+            bypassed: Vec::new(),
+        }
+    }
+
+    fn replay(&mut self) {
+        for key in std::mem::take(&mut self.held) {
+            self.press(key);
+        }
+    }
+
+    fn press(&mut self, key: Key) -> InputResult {
+        let ret = self.engine.press_key(key, &self.config);
+
+        if ret.contains(InputResult::REPLAY) {
+            self.replay();
+            return self.press(key);
+        }
+
+        if ret.contains(InputResult::HOLD) {
+            self.held.push(key);
+        } else {
+            self.held.clear();
+        }
+
+        if !ret.contains(InputResult::CONSUMED) {
+            self.bypassed.push(key);
+        }
+
+        ret
+    }
+
+    fn release(&mut self, key: Key) -> InputResult {
+        let ret = self.engine.release_key(key, &self.config);
+
+        if ret.contains(InputResult::REPLAY) {
+            self.replay();
+            return self.release(key);
+        }
+
+        ret
+    }
+
+    /// Wait for deadline of held keys like timer of frontends
+    fn wait(&mut self) {
+        if let Some(deadline) = self.engine.held_deadline(&self.config) {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+
+            if self.engine.flush_held_keys().contains(InputResult::REPLAY) {
+                self.replay();
+            }
+        }
+    }
+
+    fn tap(&mut self, key: Key) -> InputResult {
+        let ret = self.press(key);
+        self.release(key);
+        ret
+    }
+}
+
+#[test]
+fn parse() {
+    assert_eq!(
+        "C-X H".parse::<HotkeyTrigger>().unwrap(),
+        HotkeyTrigger::Sequence(vec![Key::ctrl(X), Key::normal(H)])
+    );
+    assert_eq!(
+        "J+K".parse::<HotkeyTrigger>().unwrap(),
+        HotkeyTrigger::Chord(Key::normal(J), Key::normal(K))
+    );
+    assert_eq!(
+        "Esc".parse::<HotkeyTrigger>().unwrap(),
+        HotkeyTrigger::Press(Key::normal(Esc))
+    );
+    assert!("".parse::<HotkeyTrigger>().is_err());
+    assert!("J+".parse::<HotkeyTrigger>().is_err());
+    assert_eq!(
+        HotkeyTrigger::Sequence(vec![Key::normal(Esc), Key::normal(Esc)]).to_string(),
+        "Esc Esc"
+    );
+}
+
+#[test]
+fn sequence() {
+    let mut frontend = Frontend::with_config(hotkey_config(&[(
+        "C-X H".parse::<HotkeyTrigger>().unwrap(),
+        Hotkey::new(HotkeyBehavior::Mode(InputMode::Math), HotkeyResult::Consume),
+    )]));
+
+    frontend.press(Key::normal(ControlL));
+    // Keys before last one are held
+    assert!(frontend.tap(Key::ctrl(X)).contains(InputResult::HOLD));
+    frontend.release(Key::ctrl(ControlL));
+    assert!(frontend.tap(Key::normal(H)).contains(InputResult::CONSUMED));
+    assert_eq!(frontend.engine.input_mode(), Some(InputMode::Math));
+    assert_eq!(frontend.bypassed, [Key::normal(ControlL)]);
+}
+
+#[test]
+fn sequence_mismatch() {
+    let mut frontend = Frontend::new(&["Esc Esc"], 500);
+
+    frontend.tap(Key::normal(Esc));
+    assert!(frontend.bypassed.is_empty());
+    frontend.tap(Key::normal(A));
+    // Held key is sent before next key
+    assert_eq!(frontend.bypassed, [Key::normal(Esc), Key::normal(A)]);
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+}
+
+#[test]
+fn double_tap() {
+    let mut frontend = Frontend::new(&["Esc Esc"], 500);
+
+    frontend.tap(Key::normal(Esc));
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+    frontend.tap(Key::normal(Esc));
+    assert_eq!(frontend.engine.category(), InputCategory::Hangul);
+    assert!(frontend.bypassed.is_empty());
+
+    // Repeat of held key is not a sequence, `Esc` hotkey of default config is used
+    frontend.press(Key::normal(Esc));
+    frontend.press(Key::normal(Esc));
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+    assert_eq!(frontend.bypassed, [Key::normal(Esc), Key::normal(Esc)]);
+}
+
+#[test]
+fn sequence_timeout() {
+    let mut frontend = Frontend::new(&["Esc Esc"], 10);
+
+    frontend.tap(Key::normal(Esc));
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    frontend.tap(Key::normal(Esc));
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+    // Second one is held again
+    assert_eq!(frontend.bypassed, [Key::normal(Esc)]);
+}
+
+#[test]
+fn sequence_flush() {
+    let mut frontend = Frontend::new(&["Esc Esc"], 10);
+
+    frontend.tap(Key::normal(Esc));
+    assert!(frontend.bypassed.is_empty());
+    // Held key is sent without next key
+    frontend.wait();
+    assert_eq!(frontend.bypassed, [Key::normal(Esc)]);
+    assert_eq!(frontend.engine.held_deadline(&frontend.config), None);
+}
+
+#[test]
+fn chord() {
+    let mut frontend = Frontend::new(&["J+K"], 500);
+
+    // First key is held until chord is completed
+    let ret = frontend.press(Key::normal(K));
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::HOLD));
+    frontend.press(Key::normal(J));
+    assert_eq!(frontend.engine.category(), InputCategory::Hangul);
+    assert_eq!(frontend.engine.commit_str(), "");
+    assert!(frontend.bypassed.is_empty());
+}
+
+#[test]
+fn chord_released() {
+    let mut frontend = Frontend::new(&["J+K"], 500);
+
+    // Keys which are typed one by one are not a chord
+    frontend.tap(Key::normal(J));
+    frontend.tap(Key::normal(K));
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+    assert_eq!(frontend.engine.commit_str(), "");
+    assert_eq!(frontend.bypassed, [Key::normal(J), Key::normal(K)]);
+}
+
+#[test]
+fn chord_hangul() {
+    let mut frontend = Frontend::new(&["J+K"], 500);
+    frontend.engine.set_input_category(InputCategory::Hangul);
+
+    frontend.press(Key::normal(J));
+    assert_eq!(frontend.engine.preedit_str(), "");
+    frontend.press(Key::normal(L));
+    assert_eq!(frontend.engine.commit_str(), "ㅓ");
+    assert_eq!(frontend.engine.preedit_str(), "ㅣ");
+    assert!(frontend.bypassed.is_empty());
+
+    frontend.engine.reset();
+    frontend.press(Key::normal(R));
+    frontend.tap(Key::normal(J));
+    assert_eq!(frontend.engine.preedit_str(), "거");
+    assert_eq!(frontend.engine.category(), InputCategory::Hangul);
+}
+
+#[test]
+fn chord_timeout() {
+    let mut frontend = Frontend::new(&["J+K"], 10);
+    frontend.engine.set_input_category(InputCategory::Hangul);
+
+    frontend.press(Key::normal(J));
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    frontend.press(Key::normal(K));
+    assert_eq!(frontend.engine.category(), InputCategory::Hangul);
+    assert_eq!(frontend.engine.preedit_str(), "ㅓ");
+    // Second key is held again
+    frontend.release(Key::normal(K));
+    assert_eq!(frontend.engine.commit_str(), "ㅓ");
+    assert_eq!(frontend.engine.preedit_str(), "ㅏ");
+}
+
+#[test]
+fn chord_flush() {
+    let mut frontend = Frontend::new(&["J+K"], 10);
+    frontend.engine.set_input_category(InputCategory::Hangul);
+
+    frontend.press(Key::normal(J));
+    assert_eq!(frontend.engine.preedit_str(), "");
+    frontend.wait();
+    assert_eq!(frontend.engine.preedit_str(), "ㅓ");
+    assert!(frontend.held.is_empty());
+}
+
+#[test]
+fn chord_modifier() {
+    let mut frontend = Frontend::new(&["J+K"], 500);
+
+    // Only keys in chord with same modifiers are held
+    let ret = frontend.press(Key::ctrl(J));
+    assert!(!ret.contains(InputResult::HOLD));
+    assert_eq!(frontend.bypassed, [Key::ctrl(J)]);
+    frontend.release(Key::ctrl(J));
+    frontend.bypassed.clear();
+
+    frontend.press(Key::normal(J));
+    frontend.press(Key::normal(ShiftL));
+    frontend.press(Key::shift(K));
+    assert_eq!(frontend.engine.category(), InputCategory::Latin);
+    assert_eq!(
+        frontend.bypassed,
+        [Key::normal(J), Key::normal(ShiftL), Key::shift(K)]
+    );
+}
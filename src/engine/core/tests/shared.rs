@@ -1,11 +1,32 @@
-use kime_engine_core::{Config, EngineConfig, InputCategory, InputEngine, InputResult, Key};
+use kime_engine_core::{
+    Config, EngineConfig, Hotkey, HotkeyTrigger, InputCategory, InputEngine, InputResult, Key,
+};
 use pretty_assertions::assert_eq;
 
-#[track_caller]
-pub fn test_input_impl(engine: EngineConfig, category: InputCategory, keys: &[(Key, &str, &str)]) {
+/// Default config with global hotkeys
+#[allow(dead_code)]
+pub fn hotkey_config<T: Into<HotkeyTrigger> + Clone>(hotkeys: &[(T, Hotkey)]) -> EngineConfig {
+    let mut config = EngineConfig::default();
+    for (trigger, hotkey) in hotkeys {
+        config
+            .global_hotkeys
+            .insert(trigger.clone().into(), hotkey.clone());
+    }
+    config
+}
+
+#[allow(dead_code)]
+pub fn test_engine(engine: EngineConfig, category: InputCategory) -> (InputEngine, Config) {
     let config = Config::new(engine);
     let mut engine = InputEngine::new(&config);
     engine.set_input_category(category);
+    (engine, config)
+}
+
+#[allow(dead_code)]
+#[track_caller]
+pub fn test_input_impl(engine: EngineConfig, category: InputCategory, keys: &[(Key, &str, &str)]) {
+    let (mut engine, config) = test_engine(engine, category);
 
     macro_rules! test_preedit {
         ($text:expr) => {{
@@ -49,7 +70,6 @@ pub fn test_input_impl(engine: EngineConfig, category: InputCategory, keys: &[(K
 macro_rules! define_layout_test {
     ($layout:expr, $latin_layout:expr, $category:expr) => {
         use kime_engine_backend_hangul::Addon;
-        use kime_engine_backend_latin::LatinLayout;
         use kime_engine_core::{EngineConfig, EnumSet, Hotkey, InputCategory, Key, KeyCode::*};
         use shared::test_input_impl;
 
@@ -57,7 +77,7 @@ macro_rules! define_layout_test {
         fn default_config() -> EngineConfig {
             let mut config = EngineConfig::default();
             config.hangul.layout = $layout.into();
-            config.latin.layout = $latin_layout;
+            config.latin.layout = $latin_layout.into();
             config
         }
 
@@ -87,11 +107,14 @@ macro_rules! define_layout_test {
         #[track_caller]
         fn test_input_with_hotkey(keys: &[(Key, &str, &str)], hotkeys: &[(Key, Hotkey)]) {
             let mut config = default_config();
-            config.global_hotkeys = hotkeys.iter().copied().collect();
+            config.global_hotkeys = hotkeys
+                .iter()
+                .map(|(k, v)| ((*k).into(), v.clone()))
+                .collect();
             test_input_impl(config, $category, keys);
         }
     };
     ($layout:expr) => {
-        define_layout_test!($layout, LatinLayout::Qwerty, InputCategory::Hangul);
+        define_layout_test!($layout, "Qwerty", InputCategory::Hangul);
     };
 }
@@ -0,0 +1,67 @@
+mod shared;
+
+use kime_engine_core::{
+    Config, Hotkey, HotkeyBehavior, HotkeyResult, HotkeyTrigger, InputCategory, InputEngine,
+    InputResult, Key, KeyCode::*, ModifierState,
+};
+use shared::{hotkey_config, test_engine};
+
+fn engine() -> (InputEngine, Config) {
+    test_engine(
+        hotkey_config(&[(
+            HotkeyTrigger::Tap(Key::normal(ShiftL)),
+            Hotkey::new(HotkeyBehavior::toggle_hangul_latin(), HotkeyResult::Consume),
+        )]),
+        InputCategory::Hangul,
+    )
+}
+
+#[test]
+fn parse() {
+    assert_eq!(
+        "Tap(ShiftL)".parse::<HotkeyTrigger>().unwrap(),
+        HotkeyTrigger::Tap(Key::normal(ShiftL))
+    );
+    assert_eq!(
+        "C-S-Space".parse::<HotkeyTrigger>().unwrap(),
+        HotkeyTrigger::Press(Key::new(
+            Space,
+            ModifierState::CONTROL | ModifierState::SHIFT
+        ))
+    );
+    assert_eq!(
+        HotkeyTrigger::Tap(Key::ctrl(AltR)).to_string(),
+        "Tap(C-AltR)"
+    );
+}
+
+#[test]
+fn tap() {
+    let (mut engine, config) = engine();
+
+    engine.press_key(Key::normal(R), &config);
+    assert!(!engine
+        .press_key(Key::normal(ShiftL), &config)
+        .contains(InputResult::CONSUMED));
+
+    // Some frontends send shift state with shift key itself
+    let ret = engine.release_key(Key::shift(ShiftL), &config);
+    assert!(ret.contains(InputResult::LANGUAGE_CHANGED));
+    assert!(!ret.contains(InputResult::CONSUMED));
+    assert_eq!(engine.category(), InputCategory::Latin);
+    assert_eq!(engine.commit_str(), "ㄱ");
+}
+
+#[test]
+fn tap_interrupted() {
+    let (mut engine, config) = engine();
+
+    engine.press_key(Key::normal(ShiftL), &config);
+    engine.press_key(Key::shift(R), &config);
+    engine.release_key(Key::shift(R), &config);
+    let ret = engine.release_key(Key::shift(ShiftL), &config);
+
+    assert!(!ret.contains(InputResult::LANGUAGE_CHANGED));
+    assert_eq!(engine.category(), InputCategory::Hangul);
+    assert_eq!(engine.preedit_str(), "ㄲ");
+}
@@ -0,0 +1,95 @@
+mod shared;
+
+use kime_engine_core::{
+    Config, EngineConfig, Hotkey, HotkeyBehavior, HotkeyResult, InputCategory, InputEngine,
+    InputResult, Key, KeyCode::*, KeyMap, Translation,
+};
+use shared::{hotkey_config, test_engine};
+
+const LAYER: &str = r#"
+Q: W
+S-Q: C-Space
+CapsLock: {Hotkey: !Toggle [Hangul, Latin]}
+N1: !Commit "①"
+F1: {Hotkey: [Commit, !Switch Hangul]}
+"#;
+
+fn engine(translate_hotkeys: bool) -> (InputEngine, Config) {
+    let (engine, mut config) = test_engine(
+        EngineConfig {
+            translate_hotkeys,
+            ..hotkey_config(&[(
+                Key::ctrl(Space),
+                Hotkey::new(
+                    HotkeyBehavior::Switch(InputCategory::Hangul),
+                    HotkeyResult::Consume,
+                ),
+            )])
+        },
+        InputCategory::Latin,
+    );
+    config.translation_layers[InputCategory::Latin] = Some(serde_yaml::from_str(LAYER).unwrap());
+    (engine, config)
+}
+
+#[test]
+fn parse() {
+    let layer: KeyMap<Translation> = serde_yaml::from_str(LAYER).unwrap();
+
+    assert_eq!(
+        layer.get_ref(Key::normal(Q)),
+        Some(&Translation::Key(Key::normal(W)))
+    );
+    assert_eq!(
+        layer.get_ref(Key::normal(NumOne)),
+        Some(&Translation::Commit("①".into()))
+    );
+    assert_eq!(
+        layer.get_ref(Key::normal(CapsLock)),
+        Some(&Translation::Hotkey(vec![HotkeyBehavior::Toggle(
+            InputCategory::Hangul,
+            InputCategory::Latin
+        )]))
+    );
+    assert_eq!(
+        layer.get_ref(Key::normal(F1)),
+        Some(&Translation::Hotkey(vec![
+            HotkeyBehavior::Commit,
+            HotkeyBehavior::Switch(InputCategory::Hangul)
+        ]))
+    );
+}
+
+#[test]
+fn commit_and_hotkey() {
+    let (mut engine, config) = engine(false);
+
+    let ret = engine.press_key(Key::normal(NumOne), &config);
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::HAS_COMMIT));
+    assert_eq!(engine.commit_str(), "①");
+
+    let ret = engine.press_key(Key::normal(CapsLock), &config);
+    assert!(ret.contains(InputResult::CONSUMED | InputResult::LANGUAGE_CHANGED));
+    assert_eq!(engine.category(), InputCategory::Hangul);
+
+    // Hangul category doesn't have translation layer
+    engine.press_key(Key::normal(Q), &config);
+    assert_eq!(engine.preedit_str(), "ㅂ");
+}
+
+#[test]
+fn after_hotkeys() {
+    let (mut engine, config) = engine(false);
+
+    // Hotkeys see untranslated key
+    engine.press_key(Key::shift(Q), &config);
+    assert_eq!(engine.category(), InputCategory::Latin);
+}
+
+#[test]
+fn before_hotkeys() {
+    let (mut engine, config) = engine(true);
+
+    engine.press_key(Key::shift(Q), &config);
+    assert_eq!(engine.category(), InputCategory::Hangul);
+}
@@ -2,6 +2,7 @@
 #include "str_buf.h"
 
 #include <stdio.h>
+#include <string.h>
 
 static GType KIME_TYPE_IM_CONTEXT = 0;
 // for many buggy gtk apps
@@ -11,10 +12,23 @@ static const guint BYPASS_MASK = 1 << 24;
 #if GTK_CHECK_VERSION(3, 98, 4)
 typedef GtkWidget ClientType;
 typedef GdkEvent EventType;
+#define copy_event(key) gdk_event_ref(key)
+#define free_event gdk_event_unref
+#define event_keycode(key) gdk_key_event_get_keycode(key)
+#ifdef GDK_WINDOWING_X11
+#include <gdk/x11/gdkx.h>
+#endif
 #else
 #define GDK_ALT_MASK GDK_MOD1_MASK
 typedef GdkWindow ClientType;
 typedef GdkEventKey EventType;
+#define copy_event(key) (EventType *)gdk_event_copy((GdkEvent *)key)
+#define free_event gdk_event_free
+#define event_keycode(key) (key)->hardware_keycode
+#ifdef GDK_WINDOWING_X11
+#include <X11/XKBlib.h>
+#include <gdk/gdkx.h>
+#endif
 #if GTK_CHECK_VERSION(3, 0, 0)
 gboolean gdk_device_get_num_lock_state (GdkDevice *device)
 {
@@ -35,8 +49,16 @@ static const guint NOT_ENGLISH_MASK =
 typedef struct _KeyRet {
   gboolean bypassed;
   gboolean has_preedit;
+  gboolean hold;
+  gboolean replay;
 } KeyRet;
 
+typedef struct HeldKey {
+  EventType *press;
+  // Release is sent with press when key is replayed
+  EventType *release;
+} HeldKey;
+
 typedef struct KimeSignals {
   guint commit;
   guint preedit_start;
@@ -57,6 +79,12 @@ typedef struct KimeImContext {
   KimeSignals signals;
   KimeInputEngine *engine;
   gboolean preedit_visible;
+  // ISO_Level3_Shift is pressed, used when modifier mask can't be resolved
+  gboolean level3;
+  // keys which are held by engine for sequence or chord hotkey
+  GPtrArray *held_keys;
+  // timer which flushes held keys when hotkey is timed out
+  guint hold_timer;
 
   gboolean engine_ready;
   KimeConfig *config;
@@ -68,6 +96,21 @@ typedef struct KimeImContext {
 
 #define debug(...) g_log("kime", G_LOG_LEVEL_DEBUG, __VA_ARGS__)
 
+HeldKey *held_key_new(EventType *press) {
+  HeldKey *held = g_new(HeldKey, 1);
+  held->press = copy_event(press);
+  held->release = NULL;
+  return held;
+}
+
+void held_key_free(HeldKey *held) {
+  free_event(held->press);
+  if (held->release) {
+    free_event(held->release);
+  }
+  g_free(held);
+}
+
 void update_preedit(KimeImContext *ctx) {
   KimeRustStr str = kime_engine_preedit_str(ctx->engine);
 
@@ -112,6 +155,8 @@ KeyRet process_input_result(KimeImContext *ctx, KimeInputResult ret) {
   KeyRet key_ret;
   key_ret.bypassed = (ret & KimeInputResult_CONSUMED) == 0;
   key_ret.has_preedit = (ret & KimeInputResult_HAS_PREEDIT) != 0;
+  key_ret.hold = (ret & KimeInputResult_HOLD) != 0;
+  key_ret.replay = (ret & KimeInputResult_REPLAY) != 0;
 
   if (ret & KimeInputResult_NOT_READY) {
     ctx->engine_ready = FALSE;
@@ -137,11 +182,38 @@ KeyRet process_input_result(KimeImContext *ctx, KimeInputResult ret) {
   return key_ret;
 }
 
+// Same key as XIM (`WM_CLASS` class) and wayland (app id) for app profiles
+const gchar *app_key() {
+#ifdef GDK_WINDOWING_X11
+  if (GDK_IS_X11_DISPLAY(gdk_display_get_default())) {
+#if GTK_CHECK_VERSION(3, 98, 4)
+    // GTK4 uses capitalized program name as class, profiles are matched
+    // case-insensitively
+    return g_get_prgname();
+#else
+    return gdk_get_program_class();
+#endif
+  }
+#endif
+
+  // Wayland app id is application id or program name
+  GApplication *app = g_application_get_default();
+
+  if (app && g_application_get_application_id(app)) {
+    return g_application_get_application_id(app);
+  }
+
+  return g_get_prgname();
+}
+
 void focus_in(GtkIMContext *im) {
   KIME_IM_CONTEXT(im);
 
   debug("focus_in");
 
+  // ISO_Level3_Shift release could be sent to other window
+  ctx->level3 = FALSE;
+
   kime_engine_update_layout_state(ctx->engine);
 
   if (!ctx->engine_ready) {
@@ -150,6 +222,35 @@ void focus_in(GtkIMContext *im) {
       ctx->engine_ready = TRUE;
     }
   }
+
+  if (!ctx->engine_ready) {
+    return;
+  }
+
+  // Show preedit which is kept by `on_focus_out`
+  process_input_result(ctx, kime_engine_focus_in(ctx->engine));
+
+  const gchar *app = app_key();
+
+  if (app) {
+    process_input_result(ctx, kime_engine_set_app(ctx->engine, ctx->config,
+                                                  (const uint8_t *)app,
+                                                  strlen(app)));
+  }
+
+  update_preedit(ctx);
+}
+
+void remove_hold_timer(KimeImContext *ctx) {
+  if (ctx->hold_timer) {
+    g_source_remove(ctx->hold_timer);
+    ctx->hold_timer = 0;
+  }
+}
+
+void clear_held_keys(KimeImContext *ctx) {
+  g_ptr_array_set_size(ctx->held_keys, 0);
+  remove_hold_timer(ctx);
 }
 
 void kime_reset(KimeImContext *ctx) {
@@ -157,6 +258,7 @@ void kime_reset(KimeImContext *ctx) {
   str_buf_set_str(&ctx->buf, kime_engine_commit_str(ctx->engine));
   commit(ctx);
   kime_engine_reset(ctx->engine);
+  clear_held_keys(ctx);
 }
 
 void reset(GtkIMContext *im) {
@@ -172,9 +274,12 @@ void focus_out(GtkIMContext *im) {
 
   debug("focus_out");
 
-  // Don't do reset when engine is not ready
+  clear_held_keys(ctx);
+
+  // Don't touch engine when engine is not ready
   if (ctx->engine_ready) {
-    kime_reset(ctx);
+    process_input_result(ctx, kime_engine_focus_out(ctx->engine, ctx->config));
+    update_preedit(ctx);
   }
 }
 
@@ -206,9 +311,16 @@ gboolean commit_event(KimeImContext *ctx, GdkModifierType state, guint keyval) {
   return FALSE;
 }
 
-KeyRet on_key_input(KimeImContext *ctx, guint16 code, bool numlock, KimeModifierState state) {
-  KimeInputResult ret =
-      kime_engine_press_key(ctx->engine, ctx->config, code, numlock, state);
+KeyRet on_key_input(KimeImContext *ctx, guint16 code, guint keyval,
+                    KimeModifierState state) {
+  KimeInputResult ret;
+
+  // GDK keyval is same as X keysym
+  if (kime_config_key_matching(ctx->config) == Keysym) {
+    ret = kime_engine_press_keysym(ctx->engine, ctx->config, keyval, state);
+  } else {
+    ret = kime_engine_press_key(ctx->engine, ctx->config, code, state);
+  }
 
 #if DEBUG
   debug("(%d, %d, %d)", code, state, ret);
@@ -217,24 +329,171 @@ KeyRet on_key_input(KimeImContext *ctx, guint16 code, bool numlock, KimeModifier
   return process_input_result(ctx, ret);
 }
 
+KeyRet on_key_release(KimeImContext *ctx, guint16 code, guint keyval,
+                      KimeModifierState state) {
+  KimeInputResult ret;
+
+  if (kime_config_key_matching(ctx->config) == Keysym) {
+    ret = kime_engine_release_keysym(ctx->engine, ctx->config, keyval, state);
+  } else {
+    ret = kime_engine_release_key(ctx->engine, ctx->config, code, state);
+  }
+
+  // `Tap` hotkey could commit or change preedit
+  KeyRet key_ret = process_input_result(ctx, ret);
+  update_preedit(ctx);
+
+  return key_ret;
+}
+
+#if !GTK_CHECK_VERSION(3, 98, 4) && defined(GDK_WINDOWING_X11)
+// Real modifier mask of ISO_Level3_Shift, -1 when it should be resolved again
+static gint X11_LEVEL3_MASK = -1;
+
+void x11_keys_changed(GdkKeymap *keymap, gpointer user_data) {
+  X11_LEVEL3_MASK = -1;
+}
+
+// AltGr isn't always Mod5, find modifier which has ISO_Level3_Shift
+guint x11_level3_mask(GdkDisplay *display) {
+  if (X11_LEVEL3_MASK >= 0) {
+    return X11_LEVEL3_MASK;
+  }
+
+  static gboolean keys_changed_connected = FALSE;
+
+  if (!keys_changed_connected) {
+    g_signal_connect(gdk_keymap_get_for_display(display), "keys-changed",
+                     G_CALLBACK(x11_keys_changed), NULL);
+    keys_changed_connected = TRUE;
+  }
+
+  Display *xdisplay = GDK_DISPLAY_XDISPLAY(display);
+  XModifierKeymap *modmap = XGetModifierMapping(xdisplay);
+  guint mask = 0;
+
+  for (int i = 0; i < 8 * modmap->max_keypermod; i++) {
+    KeyCode code = modmap->modifiermap[i];
+
+    if (code &&
+        XkbKeycodeToKeysym(xdisplay, code, 0, 0) == XK_ISO_Level3_Shift) {
+      mask |= 1 << (i / modmap->max_keypermod);
+    }
+  }
+
+  XFreeModifiermap(modmap);
+  X11_LEVEL3_MASK = mask;
+
+  return mask;
+}
+#endif
+
+// Whether ISO_Level3_Shift(AltGr) is held
+gboolean level3_active(KimeImContext *ctx, GdkDisplay *display,
+                       GdkModifierType state) {
+#if !GTK_CHECK_VERSION(3, 98, 4) && defined(GDK_WINDOWING_X11)
+  if (GDK_IS_X11_DISPLAY(display)) {
+    return (state & x11_level3_mask(display)) != 0;
+  }
+#endif
+
+  // Wayland and GTK4 don't tell which modifier is ISO_Level3_Shift, track its
+  // keysym instead
+  return ctx->level3;
+}
+
+gboolean filter_keypress(GtkIMContext *im, EventType *key);
+
+// Process held keys again and send them to client if engine doesn't consume them
+void replay_held_keys(KimeImContext *ctx) {
+  GPtrArray *held_keys = ctx->held_keys;
+  ctx->held_keys =
+      g_ptr_array_new_with_free_func((GDestroyNotify)held_key_free);
+
+  for (guint i = 0; i < held_keys->len; i++) {
+    HeldKey *held = g_ptr_array_index(held_keys, i);
+
+    if (!filter_keypress(GTK_IM_CONTEXT(ctx), held->press)) {
+      put_event(ctx, held->press, HANDLED_MASK | BYPASS_MASK);
+    }
+
+    // Release is never consumed, send it after press
+    if (held->release) {
+      put_event(ctx, held->release, HANDLED_MASK | BYPASS_MASK);
+    }
+  }
+
+  g_ptr_array_unref(held_keys);
+}
+
+// Keep release of held key until its press is replayed
+gboolean hold_release(KimeImContext *ctx, guint16 code, EventType *key) {
+  for (guint i = 0; i < ctx->held_keys->len; i++) {
+    HeldKey *held = g_ptr_array_index(ctx->held_keys, i);
+
+    if (!held->release && event_keycode(held->press) == code) {
+      held->release = copy_event(key);
+      return TRUE;
+    }
+  }
+
+  return FALSE;
+}
+
+gboolean on_hold_timeout(gpointer user_data);
+
+void update_hold_timer(KimeImContext *ctx) {
+  uint32_t timeout;
+
+  remove_hold_timer(ctx);
+
+  if (kime_engine_held_timeout(ctx->engine, ctx->config, &timeout)) {
+    ctx->hold_timer = g_timeout_add(timeout, on_hold_timeout, ctx);
+  }
+}
+
+// Hotkey is timed out, process held keys as normal keys
+gboolean on_hold_timeout(gpointer user_data) {
+  KimeImContext *ctx = (KimeImContext *)user_data;
+
+  debug("hold_timeout");
+
+  ctx->hold_timer = 0;
+
+  if (process_input_result(ctx, kime_engine_flush_held_keys(ctx->engine))
+          .replay) {
+    replay_held_keys(ctx);
+  }
+
+  update_preedit(ctx);
+  update_hold_timer(ctx);
+
+  return G_SOURCE_REMOVE;
+}
+
 gboolean filter_keypress(GtkIMContext *im, EventType *key) {
   KIME_IM_CONTEXT(im);
 #if GTK_CHECK_VERSION(3, 98, 4)
-  if (gdk_event_get_event_type(key) != GDK_KEY_PRESS) {
+  GdkEventType type = gdk_event_get_event_type(key);
+  if (type != GDK_KEY_PRESS && type != GDK_KEY_RELEASE) {
     return FALSE;
   }
+  gboolean release = type == GDK_KEY_RELEASE;
   guint16 code = gdk_key_event_get_keycode(key);
   guint keyval = gdk_key_event_get_keyval(key);
   GdkModifierType state = gdk_event_get_modifier_state(key);
   GdkDevice* device = gdk_event_get_device(key);
+  GdkDisplay *display = gdk_event_get_display(key);
 #else
-  if (key->type != GDK_KEY_PRESS) {
+  if (key->type != GDK_KEY_PRESS && key->type != GDK_KEY_RELEASE) {
     return FALSE;
   }
+  gboolean release = key->type == GDK_KEY_RELEASE;
   guint16 code = key->hardware_keycode;
   guint keyval = key->keyval;
   GdkModifierType state = key->state;
   GdkDevice* device = gdk_event_get_device((GdkEvent*)key);
+  GdkDisplay *display = gdk_window_get_display(key->window);
 #endif
 
   // delayed event
@@ -242,17 +501,25 @@ gboolean filter_keypress(GtkIMContext *im, EventType *key) {
     // preedit change can't mixed with commit
     update_preedit(ctx);
 
-    if (state & BYPASS_MASK) {
+    if (release) {
+      return FALSE;
+    } else if (state & BYPASS_MASK) {
       return commit_event(ctx, state, keyval);
     } else {
       return TRUE;
     }
   }
 
-  bool numlock = gdk_device_get_num_lock_state(device) == TRUE;
-
   KimeModifierState kime_state = 0;
 
+  if (gdk_device_get_num_lock_state(device) == TRUE) {
+    kime_state |= KimeModifierState_NUMLOCK;
+  }
+
+  if (state & GDK_LOCK_MASK) {
+    kime_state |= KimeModifierState_CAPSLOCK;
+  }
+
   if (state & GDK_SHIFT_MASK) {
     kime_state |= KimeModifierState_SHIFT;
   }
@@ -269,7 +536,43 @@ gboolean filter_keypress(GtkIMContext *im, EventType *key) {
     kime_state |= KimeModifierState_SUPER;
   }
 
-  KeyRet key_ret = on_key_input(ctx, code, numlock, kime_state);
+  if (level3_active(ctx, display, state)) {
+    kime_state |= KimeModifierState_ALTGR;
+  }
+
+  if (keyval == GDK_KEY_ISO_Level3_Shift) {
+    ctx->level3 = !release;
+  }
+
+  // Release is never consumed
+  if (release) {
+    if (on_key_release(ctx, code, keyval, kime_state).replay) {
+      replay_held_keys(ctx);
+      on_key_release(ctx, code, keyval, kime_state);
+      update_hold_timer(ctx);
+
+      // Send release after replayed presses
+      put_event(ctx, key, HANDLED_MASK | BYPASS_MASK);
+      return TRUE;
+    }
+
+    return hold_release(ctx, code, key);
+  }
+
+  KeyRet key_ret = on_key_input(ctx, code, keyval, kime_state);
+
+  if (key_ret.replay) {
+    replay_held_keys(ctx);
+    return filter_keypress(im, key);
+  }
+
+  if (key_ret.hold) {
+    g_ptr_array_add(ctx->held_keys, held_key_new(key));
+  } else {
+    g_ptr_array_set_size(ctx->held_keys, 0);
+  }
+
+  update_hold_timer(ctx);
 
   if (ctx->preedit_visible || key_ret.has_preedit) {
     guint mask = HANDLED_MASK;
@@ -379,6 +682,10 @@ void im_context_init(KimeImContext *ctx, KimeImContextClass *klass) {
   ctx->buf = str_buf_new();
   ctx->widget = NULL;
   ctx->preedit_visible = FALSE;
+  ctx->level3 = FALSE;
+  ctx->held_keys =
+      g_ptr_array_new_with_free_func((GDestroyNotify)held_key_free);
+  ctx->hold_timer = 0;
   ctx->engine_ready = TRUE;
   ctx->signals = klass->signals;
   ctx->engine = kime_engine_new(klass->config);
@@ -392,6 +699,8 @@ void im_context_finalize(GObject *obj) {
     g_object_unref(ctx->widget);
     ctx->widget = NULL;
   }
+  remove_hold_timer(ctx);
+  g_ptr_array_unref(ctx->held_keys);
   kime_engine_delete(ctx->engine);
 }
 
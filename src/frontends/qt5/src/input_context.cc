@@ -9,6 +9,13 @@ KimeInputContext::KimeInputContext(kime::InputEngine *engine,
                                    const kime::Config *config) {
   this->engine = engine;
   this->config = config;
+
+  QString platform = QGuiApplication::platformName();
+  this->native_lock = platform == "xcb" || platform.startsWith("wayland");
+
+  this->hold_timer.setSingleShot(true);
+  QObject::connect(&this->hold_timer, &QTimer::timeout, this,
+                   &KimeInputContext::flush_held_keys);
 }
 
 void KimeInputContext::update(Qt::InputMethodQueries queries) {}
@@ -23,6 +30,8 @@ void KimeInputContext::reset() {
   kime::kime_engine_clear_preedit(this->engine);
   this->commit_str(kime::kime_engine_commit_str(this->engine));
   kime::kime_engine_reset(this->engine);
+  this->held_keys.clear();
+  this->hold_timer.stop();
 }
 
 void KimeInputContext::setFocusObject(QObject *object) {
@@ -35,8 +44,32 @@ void KimeInputContext::setFocusObject(QObject *object) {
         this->engine_ready = true;
       }
     }
+
+    if (this->engine_ready) {
+      // Show preedit which is kept by `on_focus_out`
+      this->process_input_result(kime::kime_engine_focus_in(this->engine));
+
+      // Same key as XIM (`WM_CLASS` class) and wayland (app id), xcb uses
+      // capitalized application name as class and profiles are matched
+      // case-insensitively
+      QByteArray app;
+      if (QGuiApplication::platformName() != "xcb") {
+        app = QGuiApplication::desktopFileName().toUtf8();
+      }
+      if (app.isEmpty()) {
+        app = QCoreApplication::applicationName().toUtf8();
+      }
+      this->process_input_result(kime::kime_engine_set_app(
+          this->engine, this->config, (const uint8_t *)app.constData(),
+          app.size()));
+    }
+    // AltGr release could be sent to other window
+    this->altgr = false;
   } else if (this->focus_object && this->engine_ready) {
-    this->reset();
+    this->held_keys.clear();
+    this->hold_timer.stop();
+    this->process_input_result(
+        kime::kime_engine_focus_out(this->engine, this->config));
   }
 
   this->focus_object = object;
@@ -56,16 +89,28 @@ void KimeInputContext::invokeAction(QInputMethod::Action action,
 }
 
 bool KimeInputContext::filterEvent(const QEvent *event) {
-  if (event->type() != QEvent::KeyPress) {
+  if (event->type() != QEvent::KeyPress &&
+      event->type() != QEvent::KeyRelease) {
     return false;
   }
 
+  bool release = event->type() == QEvent::KeyRelease;
+
   auto keyevent = static_cast<const QKeyEvent *>(event);
   auto modifiers = keyevent->modifiers();
 
   kime::ModifierState state = 0;
 
-  bool numlock = modifiers.testFlag(Qt::KeyboardModifier::KeypadModifier);
+  if (modifiers.testFlag(Qt::KeyboardModifier::KeypadModifier)) {
+    state |= kime::ModifierState_NUMLOCK;
+  }
+
+  // Qt doesn't expose CapsLock, check LockMask of native modifiers which is
+  // X11 state on xcb and serialized xkb mods on wayland, both have Lock as
+  // second real modifier
+  if (this->native_lock && keyevent->nativeModifiers() & 0x2) {
+    state |= kime::ModifierState_CAPSLOCK;
+  }
 
   if (modifiers.testFlag(Qt::KeyboardModifier::ControlModifier)) {
     state |= kime::ModifierState_CONTROL;
@@ -83,12 +128,107 @@ bool KimeInputContext::filterEvent(const QEvent *event) {
     state |= kime::ModifierState_SUPER;
   }
 
-  kime::InputResult ret = kime_engine_press_key(
-      this->engine, this->config, (uint16_t)keyevent->nativeScanCode(), numlock, state);
+  // `GroupSwitchModifier` is Mode_switch not AltGr, track ISO_Level3_Shift
+  // keysym instead
+  if (this->altgr) {
+    state |= kime::ModifierState_ALTGR;
+  }
+
+  if (keyevent->nativeVirtualKey() == 0xFE03) {
+    this->altgr = !release;
+  }
+
+  kime::InputResult ret;
+
+  // Release is only used for `Tap` hotkeys and never consumed
+  if (release) {
+    if (kime::kime_config_key_matching(this->config) ==
+        kime::KeyMatching::Keysym) {
+      ret = kime::kime_engine_release_keysym(
+          this->engine, this->config, keyevent->nativeVirtualKey(), state);
+    } else {
+      ret = kime::kime_engine_release_key(this->engine, this->config,
+                                          (uint16_t)keyevent->nativeScanCode(),
+                                          state);
+    }
+
+    if (ret & kime::InputResult_REPLAY) {
+      this->replay_held_keys();
+      return this->filterEvent(event);
+    }
+
+    this->process_input_result(ret);
+    return false;
+  }
+
+  // Native virtual key is X keysym on both xcb and wayland
+  if (kime::kime_config_key_matching(this->config) == kime::KeyMatching::Keysym) {
+    ret = kime::kime_engine_press_keysym(this->engine, this->config,
+                                         keyevent->nativeVirtualKey(), state);
+  } else {
+    ret = kime::kime_engine_press_key(this->engine, this->config,
+                                      (uint16_t)keyevent->nativeScanCode(),
+                                      state);
+  }
+
+  if (ret & kime::InputResult_REPLAY) {
+    this->replay_held_keys();
+    return this->filterEvent(event);
+  }
+
+  if (ret & kime::InputResult_HOLD) {
+    this->held_keys.push_back(std::make_unique<QKeyEvent>(
+        keyevent->type(), keyevent->key(), keyevent->modifiers(),
+        keyevent->nativeScanCode(), keyevent->nativeVirtualKey(),
+        keyevent->nativeModifiers(), keyevent->text(),
+        keyevent->isAutoRepeat(), keyevent->count()));
+  } else {
+    this->held_keys.clear();
+  }
+
+  this->update_hold_timer();
 
   return this->process_input_result(ret);
 }
 
+// Process held keys again and send them to client if engine doesn't consume
+// them
+void KimeInputContext::replay_held_keys() {
+  auto held_keys = std::move(this->held_keys);
+  this->held_keys.clear();
+
+  for (auto &key : held_keys) {
+    if (!this->filterEvent(key.get())) {
+      QObject *object = qApp->focusObject();
+      if (object) {
+        QCoreApplication::sendEvent(object, key.get());
+      }
+    }
+  }
+}
+
+// Hotkey is timed out, process held keys as normal keys
+void KimeInputContext::flush_held_keys() {
+  kime::InputResult ret = kime::kime_engine_flush_held_keys(this->engine);
+  this->process_input_result(ret);
+
+  if (ret & kime::InputResult_REPLAY) {
+    this->replay_held_keys();
+  }
+
+  this->update_hold_timer();
+}
+
+void KimeInputContext::update_hold_timer() {
+  uint32_t timeout;
+
+  if (kime::kime_engine_held_timeout(this->engine, this->config, &timeout)) {
+    this->hold_timer.start(timeout);
+  } else {
+    this->hold_timer.stop();
+  }
+}
+
 void KimeInputContext::preedit_str(kime::RustStr s) {
   this->focus_object = qApp->focusObject();
   if (!this->focus_object) {
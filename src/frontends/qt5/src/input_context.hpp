@@ -2,9 +2,14 @@
 
 #include "kime-qt5.hpp"
 
+#include <QtCore/QTimer>
 #include <QtGui/QInputMethodEvent>
+#include <QtGui/QKeyEvent>
 #include <qpa/qplatforminputcontext.h>
 
+#include <memory>
+#include <vector>
+
 class KimeEventFilter;
 
 class KimeInputContext : public QPlatformInputContext {
@@ -27,12 +32,21 @@ private:
   void commit_str(kime::RustStr s);
   void preedit_str(kime::RustStr s);
   bool process_input_result(kime::InputResult ret);
+  void replay_held_keys();
+  void flush_held_keys();
+  void update_hold_timer();
 
   bool visible = false;
   bool engine_ready = true;
+  bool altgr = false;
+  bool native_lock = false;
   QList<QInputMethodEvent::Attribute> attributes;
   const kime::Config *config = nullptr;
   kime::InputEngine *engine = nullptr;
   QObject *focus_object = nullptr;
   KimeEventFilter *filter = nullptr;
+  // keys which are held by engine for sequence or chord hotkey
+  std::vector<std::unique_ptr<QKeyEvent>> held_keys;
+  // timer which flushes held keys when hotkey is timed out
+  QTimer hold_timer;
 };
@@ -1,9 +1,21 @@
-use std::time::{Duration, Instant};
+mod popup;
+mod toplevel;
+mod xkb;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use wayland_client::{
     event_enum,
-    protocol::{wl_keyboard::KeyState, wl_seat::WlSeat},
-    DispatchData, Display, Filter, GlobalManager, Main,
+    protocol::{
+        wl_compositor::WlCompositor,
+        wl_keyboard::{KeyState, KeymapFormat},
+        wl_seat::WlSeat,
+        wl_shm::WlShm,
+    },
+    Display, Filter, GlobalError, GlobalEvent, GlobalManager, Main,
 };
 
 use wayland_protocols::misc::zwp_input_method_v2::client::{
@@ -11,6 +23,10 @@ use wayland_protocols::misc::zwp_input_method_v2::client::{
     zwp_input_method_manager_v2::ZwpInputMethodManagerV2,
     zwp_input_method_v2::{Event as ImEvent, ZwpInputMethodV2},
 };
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ContentHint, ContentPurpose,
+};
+use wayland_protocols::wlr::unstable::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1;
 use zwp_virtual_keyboard::virtual_keyboard_unstable_v1::{
     zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
     zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
@@ -18,9 +34,13 @@ use zwp_virtual_keyboard::virtual_keyboard_unstable_v1::{
 
 use kime_engine_cffi::*;
 
-use mio::{unix::SourceFd, Events as MioEvents, Interest, Poll, Token};
+use mio::{unix::SourceFd, Events as MioEvents, Interest, Poll, Registry, Token};
 use mio_timerfd::{ClockId, TimerFd};
 
+use popup::Popup;
+use toplevel::ToplevelTracker;
+use xkb::Keymap;
+
 event_enum! {
     Events |
     Key => ZwpInputMethodKeyboardGrabV2,
@@ -30,6 +50,7 @@ event_enum! {
 struct InputMethodState {
     activate: bool,
     deactivate: bool,
+    content_type: Option<(ContentHint, ContentPurpose)>,
 }
 
 impl Default for InputMethodState {
@@ -37,10 +58,28 @@ impl Default for InputMethodState {
         Self {
             activate: false,
             deactivate: false,
+            content_type: None,
         }
     }
 }
 
+/// Text input which shouldn't be composed by engine e.g. password, PIN, number
+fn is_direct_content(hint: ContentHint, purpose: ContentPurpose) -> bool {
+    if hint.intersects(ContentHint::SensitiveData | ContentHint::HiddenText) {
+        return true;
+    }
+
+    matches!(
+        purpose,
+        ContentPurpose::Password
+            | ContentPurpose::Pin
+            | ContentPurpose::Digits
+            | ContentPurpose::Number
+            | ContentPurpose::Phone
+            | ContentPurpose::Email
+    )
+}
+
 #[derive(Clone, Copy)]
 struct RepeatInfo {
     /// The rate of repeating keys in characters per second
@@ -76,6 +115,14 @@ enum PressState {
     },
 }
 
+/// Key which is held by engine for sequence or chord hotkey
+struct HeldKey {
+    time: u32,
+    key: u32,
+    /// Release is sent with press when key is replayed
+    released: bool,
+}
+
 impl PressState {
     fn is_pressing(&self, query_key: u32) -> bool {
         if let PressState::Pressing { key, .. } = self {
@@ -95,12 +142,24 @@ struct KimeContext {
     vk: Main<ZwpVirtualKeyboardV1>,
     im: Main<ZwpInputMethodV2>,
     grab: Main<ZwpInputMethodKeyboardGrabV2>,
-    numlock: bool,
+    popup: Option<Popup>,
     engine_ready: bool,
-    keymap_init: bool,
+    keymap: Option<Keymap>,
+    /// Last keymap uploaded to virtual keyboard
+    keymap_data: Vec<u8>,
     grab_activate: bool,
+    /// Input method is inert and should be rebound
+    unavailable: bool,
+    /// Bypass engine for current text input, category is untouched so it's restored after focus
+    /// leaves the field
+    direct: bool,
+    /// App id of activated toplevel which is used for app profile
+    app: Option<String>,
+    /// Keys which are not sent to client yet
+    held_keys: Vec<HeldKey>,
+    /// Fires when held keys should be sent without completing hotkey
+    hold_timer: TimerFd,
     serial: u32,
-    // Have to concern Multi seats?
 
     // Key repeat contexts
     timer: TimerFd,
@@ -123,32 +182,46 @@ impl KimeContext {
         vk: Main<ZwpVirtualKeyboardV1>,
         im: Main<ZwpInputMethodV2>,
         grab: Main<ZwpInputMethodKeyboardGrabV2>,
+        popup_globals: Option<(Main<WlCompositor>, Main<WlShm>)>,
         timer: TimerFd,
+        hold_timer: TimerFd,
     ) -> Self {
         let config = Config::load();
+        let mut engine = InputEngine::new(&config);
+
+        let popup = popup_globals.and_then(|(compositor, shm)| {
+            let (_, _, font_size) = config.xim_font();
+            Popup::new(&compositor, shm, &im, config.candidate_font(), font_size)
+        });
+
+        // Draw candidate list in popup surface instead of candidate window
+        engine.set_frontend_candidate(popup.is_some());
+
         Self {
-            engine: InputEngine::new(&config),
+            engine,
             config,
             mod_state: 0,
             current_state: InputMethodState::default(),
             pending_state: InputMethodState::default(),
             serial: 0,
-            numlock: false,
             engine_ready: true,
-            keymap_init: false,
+            keymap: None,
+            keymap_data: Vec::new(),
             grab_activate: false,
+            unavailable: false,
+            direct: false,
+            app: None,
+            held_keys: Vec::new(),
+            hold_timer,
             vk,
             im,
             grab,
+            popup,
             timer,
             repeat_state: None,
         }
     }
 
-    pub fn new_data<'a>(data: &'a mut DispatchData) -> &'a mut Self {
-        data.get::<Self>().unwrap()
-    }
-
     fn process_input_result(&mut self, ret: InputResult) -> bool {
         if ret & InputResult_NOT_READY != 0 {
             self.engine_ready = false;
@@ -170,6 +243,7 @@ impl KimeContext {
             self.engine.clear_commit();
         }
 
+        self.update_popup();
         self.commit();
 
         ret & InputResult_CONSUMED == 0
@@ -179,6 +253,26 @@ impl KimeContext {
         self.im.commit(self.serial);
     }
 
+    /// Change focused app and apply its profile when input method is activated
+    fn set_app(&mut self, app: &str) {
+        self.app = Some(app.into());
+
+        if self.grab_activate {
+            self.apply_app();
+        }
+    }
+
+    fn apply_app(&mut self) {
+        if !self.engine_ready {
+            return;
+        }
+
+        if let Some(app) = self.app.as_deref() {
+            let ret = self.engine.set_app(app, &self.config);
+            self.process_input_result(ret);
+        }
+    }
+
     fn commit_string(&mut self, s: String) {
         if !s.is_empty() {
             self.im.commit_string(s);
@@ -194,6 +288,46 @@ impl KimeContext {
         self.im.set_preedit_string(s, 0, len as _);
     }
 
+    fn update_popup(&mut self) {
+        let engine = &self.engine;
+        let popup = match self.popup.as_mut() {
+            Some(popup) => popup,
+            None => return,
+        };
+
+        let len = engine.candidate_len();
+
+        if len != 0 {
+            let cursor = engine.candidate_cursor();
+            let start = cursor - cursor % KIME_CANDIDATE_PAGE_SIZE;
+            let end = (start + KIME_CANDIDATE_PAGE_SIZE).min(len);
+
+            let mut lines: Vec<String> = (start..end)
+                .map(|i| {
+                    let (value, description) = engine.candidate(i);
+                    format!("{}. {} {}", (i - start + 1) % 10, value, description)
+                })
+                .collect();
+
+            lines.push(format!(
+                "{}/{}",
+                start / KIME_CANDIDATE_PAGE_SIZE + 1,
+                (len - 1) / KIME_CANDIDATE_PAGE_SIZE + 1
+            ));
+
+            popup.draw(&lines, Some(cursor - start));
+        } else if let Some(mode) = engine.input_mode() {
+            let hint = match mode {
+                InputMode::Math => "Math",
+                InputMode::Hanja => "Hanja",
+                InputMode::Emoji => "Emoji",
+            };
+            popup.draw(&[hint.into()], None);
+        } else {
+            popup.hide();
+        }
+    }
+
     pub fn handle_im_ev(&mut self, ev: ImEvent) {
         match ev {
             ImEvent::Activate => {
@@ -202,27 +336,66 @@ impl KimeContext {
             ImEvent::Deactivate => {
                 self.pending_state.deactivate = true;
             }
+            ImEvent::ContentType { hint, purpose } => {
+                self.pending_state.content_type = Some((hint, purpose));
+            }
             ImEvent::Unavailable => {
                 log::error!("Receive Unavailable event is another server already running?");
-                panic!("Unavailable")
+                self.unavailable = true;
+                self.grab_activate = false;
             }
             ImEvent::Done => {
                 self.serial += 1;
+
+                if self.pending_state.activate {
+                    // Activate resets content type to initial value
+                    self.direct = false;
+                }
+
+                if let Some((hint, purpose)) = self.pending_state.content_type {
+                    let direct = is_direct_content(hint, purpose);
+
+                    if direct && !self.direct {
+                        log::debug!("Enter direct mode for {:?} content", purpose);
+                        if self.engine_ready {
+                            self.engine.reset();
+                        }
+                        self.clear_held_keys();
+                        self.clear_preedit();
+                        self.update_popup();
+                        self.commit();
+                    }
+
+                    self.direct = direct;
+                }
+
                 if !self.current_state.activate && self.pending_state.activate {
                     self.engine.update_layout_state();
-                    if !self.engine_ready {
-                        if self.engine.check_ready() {
-                            let ret = self.engine.end_ready();
-                            self.process_input_result(ret);
-                            self.engine_ready = true;
-                        }
+                    if !self.engine_ready && self.engine.check_ready() {
+                        let ret = self.engine.end_ready();
+                        self.process_input_result(ret);
+                        self.engine_ready = true;
+                    }
+                    // Show preedit which is kept by `on_focus_out`, direct content never shows it
+                    if self.engine_ready && !self.direct {
+                        let ret = self.engine.focus_in();
+                        self.process_input_result(ret);
                     }
+                    self.apply_app();
                     self.grab_activate = true;
                 } else if !self.current_state.deactivate && self.pending_state.deactivate {
-                    // Focus lost, reset states
+                    // Focus lost, handle preedit with `on_focus_out`
+                    self.clear_held_keys();
                     if self.engine_ready {
-                        self.engine.reset();
+                        let ret = self.engine.focus_out(&self.config);
+                        if ret & InputResult_HAS_COMMIT != 0 {
+                            self.commit_string(self.engine.commit_str().into());
+                            self.engine.clear_commit();
+                        }
                     }
+                    self.clear_preedit();
+                    self.update_popup();
+                    self.commit();
                     self.grab_activate = false;
 
                     // Input deactivated, stop repeating
@@ -240,9 +413,23 @@ impl KimeContext {
     pub fn handle_key_ev(&mut self, ev: KeyEvent) {
         match ev {
             KeyEvent::Keymap { fd, format, size } => {
-                if !self.keymap_init {
-                    self.vk.keymap(format as _, fd, size);
-                    self.keymap_init = true;
+                if format == KeymapFormat::XkbV1 {
+                    match xkb::read_keymap(fd, size) {
+                        // Uploading same keymap again makes compositor send it back to grab
+                        Ok(data) if data != self.keymap_data => {
+                            match Keymap::new(&data) {
+                                Some(keymap) => self.keymap = Some(keymap),
+                                // Previous keymap is still better than fixed masks
+                                None => log::warn!("Can't compile xkb keymap"),
+                            }
+                            self.vk.keymap(format as _, fd, size);
+                            self.keymap_data = data;
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("Can't read xkb keymap: {}", e);
+                        }
+                    }
                 }
                 unsafe {
                     libc::close(fd);
@@ -253,15 +440,10 @@ impl KimeContext {
             } => {
                 // NOTE: Never read `serial` of KeyEvent. You should rely on serial of KimeContext
                 if state == KeyState::Pressed {
-                    if self.grab_activate {
-                        let ret = self.engine.press_key(
-                            &self.config,
-                            (key + 8) as u16,
-                            self.numlock,
-                            self.mod_state,
-                        );
-
-                        let bypassed = self.process_input_result(ret);
+                    // Keys in direct mode never reach engine nor logger
+                    if self.grab_activate && !self.direct {
+                        let bypassed = self.press_key(time, key);
+                        self.update_hold_timer();
 
                         if bypassed {
                             // Bypassed key's repeat will be handled by the clients.
@@ -269,6 +451,14 @@ impl KimeContext {
                             // Reference:
                             //   https://github.com/swaywm/sway/pull/4932#issuecomment-774113129
                             self.vk.key(time, key, state as _);
+
+                            // Held key can be bypassed by its repeat
+                            if let Some((.., ref mut press_state)) = self.repeat_state {
+                                if press_state.is_pressing(key) {
+                                    self.timer.disarm().unwrap();
+                                    *press_state = PressState::NotPressing;
+                                }
+                            }
                         } else {
                             // If the key was not bypassed by IME, key repeat should be handled by the
                             // IME. Start waiting for the key hold timer event.
@@ -289,7 +479,7 @@ impl KimeContext {
                             }
                         }
                     } else {
-                        // not activated so just skip
+                        // not activated or direct mode so just skip
                         self.vk.key(time, key, state as _);
                     }
                 } else {
@@ -301,7 +491,38 @@ impl KimeContext {
                         }
                     }
 
-                    self.vk.key(time, key, state as _);
+                    // Client doesn't know press of held key yet
+                    let held = match self.held_keys.iter_mut().find(|held| held.key == key) {
+                        Some(held) => {
+                            held.released = true;
+                            true
+                        }
+                        None => false,
+                    };
+
+                    // Release is only used for `Tap` hotkeys and never consumed
+                    if self.grab_activate && !self.direct {
+                        let preedit = self.engine.preedit_str().to_string();
+                        let mut ret = self.release_key(key);
+
+                        if ret & InputResult_REPLAY != 0 {
+                            self.replay_held_keys();
+                            self.update_hold_timer();
+                            ret = self.release_key(key);
+                        }
+
+                        // Most releases change nothing, don't send preedit and commit requests
+                        // to compositor for them
+                        if ret & (InputResult_HAS_COMMIT | InputResult_LANGUAGE_CHANGED) != 0
+                            || self.engine.preedit_str() != preedit
+                        {
+                            self.process_input_result(ret);
+                        }
+                    }
+
+                    if !held {
+                        self.vk.key(time, key, state as _);
+                    }
                 }
             }
             KeyEvent::Modifiers {
@@ -311,21 +532,13 @@ impl KimeContext {
                 group,
                 ..
             } => {
-                self.mod_state = 0;
-                if mods_depressed & 0x1 != 0 {
-                    self.mod_state |= ModifierState_SHIFT;
-                }
-                if mods_depressed & 0x4 != 0 {
-                    self.mod_state |= ModifierState_CONTROL;
-                }
-                if mods_depressed & 0x8 != 0 {
-                    self.mod_state |= ModifierState_ALT;
-                }
-                if mods_depressed & 0x40 != 0 {
-                    self.mod_state |= ModifierState_SUPER;
-                }
-
-                self.numlock = mods_depressed & 0x10 != 0;
+                self.mod_state = match self.keymap.as_mut() {
+                    Some(keymap) => {
+                        keymap.update_mask(mods_depressed, mods_latched, mods_locked, group);
+                        keymap.modifier_state()
+                    }
+                    None => xkb::fallback_modifier_state(mods_depressed, mods_latched, mods_locked),
+                };
 
                 self.vk
                     .modifiers(mods_depressed, mods_latched, mods_locked, group);
@@ -347,6 +560,89 @@ impl KimeContext {
         }
     }
 
+    /// Press key to engine, returns `true` when key should be sent to client
+    fn press_key(&mut self, time: u32, key: u32) -> bool {
+        let ret = match (self.config.key_matching(), self.keymap.as_ref()) {
+            (KeyMatching::Keysym, Some(keymap)) => {
+                self.engine
+                    .press_keysym(&self.config, keymap.keysym(key + 8), self.mod_state)
+            }
+            _ => self
+                .engine
+                .press_key(&self.config, (key + 8) as u16, self.mod_state),
+        };
+
+        if ret & InputResult_REPLAY != 0 {
+            self.replay_held_keys();
+            return self.press_key(time, key);
+        }
+
+        if ret & InputResult_HOLD != 0 {
+            self.held_keys.push(HeldKey {
+                time,
+                key,
+                released: false,
+            });
+        } else {
+            self.held_keys.clear();
+        }
+
+        self.process_input_result(ret)
+    }
+
+    fn release_key(&mut self, key: u32) -> InputResult {
+        match (self.config.key_matching(), self.keymap.as_ref()) {
+            (KeyMatching::Keysym, Some(keymap)) => {
+                self.engine
+                    .release_keysym(&self.config, keymap.keysym(key + 8), self.mod_state)
+            }
+            _ => self
+                .engine
+                .release_key(&self.config, (key + 8) as u16, self.mod_state),
+        }
+    }
+
+    /// Press held keys again and send them to client if engine doesn't consume them
+    fn replay_held_keys(&mut self) {
+        for held in std::mem::take(&mut self.held_keys) {
+            if self.press_key(held.time, held.key) {
+                self.vk.key(held.time, held.key, KeyState::Pressed as _);
+
+                if held.released {
+                    self.vk.key(held.time, held.key, KeyState::Released as _);
+                }
+            }
+        }
+    }
+
+    fn clear_held_keys(&mut self) {
+        self.held_keys.clear();
+        self.hold_timer.disarm().unwrap();
+    }
+
+    /// Arm hold timer with deadline of held keys
+    fn update_hold_timer(&mut self) {
+        match self.engine.held_timeout(&self.config) {
+            // Zero timeout disarms timer
+            Some(timeout) => self
+                .hold_timer
+                .set_timeout(&timeout.max(Duration::from_millis(1)))
+                .unwrap(),
+            None => self.hold_timer.disarm().unwrap(),
+        }
+    }
+
+    /// Hotkey is not completed in time, send held keys to client
+    pub fn handle_hold_timer_ev(&mut self) -> std::io::Result<()> {
+        self.hold_timer.read()?;
+
+        if self.engine.flush_held_keys() & InputResult_REPLAY != 0 {
+            self.replay_held_keys();
+        }
+
+        Ok(())
+    }
+
     pub fn handle_timer_ev(&mut self) -> std::io::Result<()> {
         // Read timer, this MUST be called or timer will be broken
         let overrun_count = self.timer.read()?;
@@ -388,74 +684,275 @@ impl KimeContext {
     }
 }
 
-fn main() {
-    kime_version::cli_boilerplate!((),);
+/// Globals shared by every seat
+struct SeatGlobals {
+    im_manager: Main<ZwpInputMethodManagerV2>,
+    vk_manager: Main<ZwpVirtualKeyboardManagerV1>,
+    popup: Option<(Main<WlCompositor>, Main<WlShm>)>,
+}
 
-    assert!(
-        kime_engine_cffi::check_api_version(),
-        "Engine version mismatched"
-    );
+const RETRY_DELAY_MIN: Duration = Duration::from_secs(1);
+const RETRY_DELAY_MAX: Duration = Duration::from_secs(60);
 
-    let display = Display::connect_to_env().expect("Failed to connect wayland display");
+/// Every seat has its own `KimeContext` with independent engine state
+struct KimeState {
+    /// `None` until initial roundtrip is done
+    globals: Option<SeatGlobals>,
+    /// Key is global name of seat which is also used as token of timers
+    seats: HashMap<u32, Main<WlSeat>>,
+    contexts: HashMap<u32, KimeContext>,
+    registry: Registry,
+    toplevels: ToplevelTracker,
+
+    // Rebind input method of seats which received `Unavailable`
+    retry_seats: Vec<u32>,
+    retry_timer: TimerFd,
+    retry_delay: Duration,
+}
+
+impl KimeState {
+    fn new(registry: Registry, mut retry_timer: TimerFd) -> std::io::Result<Self> {
+        registry.register(&mut retry_timer, POLL_RETRY, Interest::READABLE)?;
+
+        Ok(Self {
+            globals: None,
+            seats: HashMap::new(),
+            contexts: HashMap::new(),
+            registry,
+            toplevels: ToplevelTracker::default(),
+            retry_seats: Vec::new(),
+            retry_timer,
+            retry_delay: RETRY_DELAY_MIN,
+        })
+    }
+
+    fn set_globals(&mut self, globals: SeatGlobals) {
+        self.globals = Some(globals);
+
+        let names: Vec<u32> = self.seats.keys().copied().collect();
+        for name in names {
+            self.add_context(name);
+        }
+    }
+
+    fn add_seat(&mut self, name: u32, seat: Main<WlSeat>) {
+        self.seats.insert(name, seat);
+        self.add_context(name);
+    }
+
+    fn remove_seat(&mut self, name: u32) {
+        self.seats.remove(&name);
+        self.retry_seats.retain(|n| *n != name);
+        self.remove_context(name);
+    }
+
+    fn add_context(&mut self, name: u32) {
+        if let Err(e) = self.try_add_context(name) {
+            log::error!("Can't add seat {}: {}", name, e);
+
+            if let Err(e) = self.schedule_retry(std::iter::once(name)) {
+                log::error!("Can't schedule retry: {}", e);
+            }
+        }
+    }
+
+    fn try_add_context(&mut self, name: u32) -> std::io::Result<()> {
+        let (globals, seat) = match (self.globals.as_ref(), self.seats.get(&name)) {
+            (Some(globals), Some(seat)) => (globals, seat),
+            _ => return Ok(()),
+        };
+
+        let mut timer = TimerFd::new(ClockId::Monotonic)?;
+        self.registry
+            .register(&mut timer, repeat_token(name), Interest::READABLE)?;
+        let mut hold_timer = TimerFd::new(ClockId::Monotonic)?;
+        self.registry
+            .register(&mut hold_timer, hold_token(name), Interest::READABLE)?;
+
+        let filter = Filter::new(move |ev, _filter, mut data| {
+            let state = data.get::<KimeState>().unwrap();
+            let ctx = match state.contexts.get_mut(&name) {
+                Some(ctx) => ctx,
+                None => return,
+            };
+
+            match ev {
+                Events::Key { event, .. } => {
+                    ctx.handle_key_ev(event);
+                }
+                Events::Im { event, .. } => {
+                    ctx.handle_im_ev(event);
+                }
+            }
+        });
+
+        let vk = globals.vk_manager.create_virtual_keyboard(seat);
+        let im = globals.im_manager.get_input_method(seat);
+        let grab = im.grab_keyboard();
+        grab.assign(filter.clone());
+        im.assign(filter);
+
+        log::info!("Add seat {}", name);
+
+        let mut ctx = KimeContext::new(vk, im, grab, globals.popup.clone(), timer, hold_timer);
+        ctx.app = self.toplevels.active_app().map(Into::into);
+        self.contexts.insert(name, ctx);
+
+        Ok(())
+    }
+
+    fn remove_context(&mut self, name: u32) {
+        if let Some(mut ctx) = self.contexts.remove(&name) {
+            log::info!("Remove seat {}", name);
+            if let Err(e) = self.registry.deregister(&mut ctx.timer) {
+                log::warn!("Can't deregister timer: {}", e);
+            }
+            if let Err(e) = self.registry.deregister(&mut ctx.hold_timer) {
+                log::warn!("Can't deregister hold timer: {}", e);
+            }
+        }
+    }
+
+    fn set_active_app(&mut self, app: &str) {
+        log::debug!("Activated app: {}", app);
+
+        for ctx in self.contexts.values_mut() {
+            ctx.set_app(app);
+        }
+    }
+
+    /// Drop contexts which received `Unavailable` and schedule rebinding them
+    fn check_unavailable(&mut self) -> std::io::Result<()> {
+        if self.contexts.values().any(|ctx| ctx.grab_activate) {
+            self.retry_delay = RETRY_DELAY_MIN;
+        }
+
+        let names: Vec<u32> = self
+            .contexts
+            .iter()
+            .filter_map(|(name, ctx)| if ctx.unavailable { Some(*name) } else { None })
+            .collect();
+
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        for name in names.iter() {
+            self.remove_context(*name);
+        }
+
+        self.schedule_retry(names)
+    }
+
+    /// Rebind input method of seats after backoff delay
+    fn schedule_retry(&mut self, names: impl IntoIterator<Item = u32>) -> std::io::Result<()> {
+        self.retry_seats.extend(names);
+
+        log::info!("Retry binding input method after {:?}", self.retry_delay);
+        self.retry_timer.set_timeout(&self.retry_delay)?;
+        self.retry_delay = (self.retry_delay * 2).min(RETRY_DELAY_MAX);
+
+        Ok(())
+    }
+
+    fn handle_retry_ev(&mut self) -> std::io::Result<()> {
+        self.retry_timer.read()?;
+
+        for name in std::mem::take(&mut self.retry_seats) {
+            self.add_context(name);
+        }
+
+        Ok(())
+    }
+}
+
+fn global_error(name: &str, e: GlobalError) -> std::io::Error {
+    std::io::Error::other(format!("Can't load {}: {}", name, e))
+}
+
+const POLL_WAYLAND: Token = Token(usize::MAX);
+const POLL_RETRY: Token = Token(usize::MAX - 1);
+
+/// Timers of seat use even and odd tokens
+fn repeat_token(name: u32) -> Token {
+    Token(name as usize * 2)
+}
+
+fn hold_token(name: u32) -> Token {
+    Token(name as usize * 2 + 1)
+}
+
+/// Run server until wayland connection is broken
+fn run(display: Display) -> std::io::Result<()> {
     let mut event_queue = display.create_event_queue();
     let attached_display = display.attach(event_queue.token());
-    let globals = GlobalManager::new(&attached_display);
 
-    event_queue.sync_roundtrip(&mut (), |_, _, _| ()).unwrap();
+    // Initialize epoll() object
+    let mut poll = Poll::new()?;
+    let registry = poll.registry();
 
-    let seat = globals.instantiate_exact::<WlSeat>(1).expect("Load Seat");
-    let im_manager = globals
-        .instantiate_exact::<ZwpInputMethodManagerV2>(1)
-        .expect("Load InputManager");
-    let vk_manager = globals
-        .instantiate_exact::<ZwpVirtualKeyboardManagerV1>(1)
-        .expect("Load VirtualKeyboardManager");
+    registry.register(
+        &mut SourceFd(&display.get_connection_fd()),
+        POLL_WAYLAND,
+        Interest::READABLE | Interest::WRITABLE,
+    )?;
 
-    let filter = Filter::new(|ev, _filter, mut data| {
-        let ctx = KimeContext::new_data(&mut data);
+    let mut state = KimeState::new(registry.try_clone()?, TimerFd::new(ClockId::Monotonic)?)?;
 
-        match ev {
-            Events::Key { event, .. } => {
-                ctx.handle_key_ev(event);
+    // Seats can be added or removed at runtime
+    let globals = GlobalManager::new_with_cb(&attached_display, |event, registry, mut data| {
+        let state = match data.get::<KimeState>() {
+            Some(state) => state,
+            None => return,
+        };
+
+        match event {
+            GlobalEvent::New { id, interface, .. } if interface == "wl_seat" => {
+                state.add_seat(id, registry.bind::<WlSeat>(1, id));
             }
-            Events::Im { event, .. } => {
-                ctx.handle_im_ev(event);
+            GlobalEvent::Removed { id, interface } if interface == "wl_seat" => {
+                state.remove_seat(id);
             }
+            _ => {}
         }
     });
 
-    let vk = vk_manager.create_virtual_keyboard(&seat);
-    let im = im_manager.get_input_method(&seat);
-    let grab = im.grab_keyboard();
-    grab.assign(filter.clone());
-    im.assign(filter);
+    event_queue.sync_roundtrip(&mut state, |_, _, _| ())?;
 
-    // Initialize timer
-    let mut timer = TimerFd::new(ClockId::Monotonic).expect("Initialize timer");
+    // Compositor may not support input method yet, reconnect later instead of aborting
+    let im_manager = globals
+        .instantiate_exact::<ZwpInputMethodManagerV2>(1)
+        .map_err(|e| global_error("InputManager", e))?;
+    let vk_manager = globals
+        .instantiate_exact::<ZwpVirtualKeyboardManagerV1>(1)
+        .map_err(|e| global_error("VirtualKeyboardManager", e))?;
 
-    // Initialize epoll() object
-    let mut poll = Poll::new().expect("Initialize epoll()");
-    let registry = poll.registry();
+    let popup = match (
+        globals.instantiate_range::<WlCompositor>(1, 4),
+        globals.instantiate_exact::<WlShm>(1),
+    ) {
+        (Ok(compositor), Ok(shm)) => Some((compositor, shm)),
+        _ => {
+            log::warn!("Can't load wl_compositor or wl_shm, popup is disabled");
+            None
+        }
+    };
 
-    const POLL_WAYLAND: Token = Token(0);
-    registry
-        .register(
-            &mut SourceFd(&display.get_connection_fd()),
-            POLL_WAYLAND,
-            Interest::READABLE | Interest::WRITABLE,
-        )
-        .expect("Register wayland socket to the epoll()");
-
-    const POLL_TIMER: Token = Token(1);
-    registry
-        .register(&mut timer, POLL_TIMER, Interest::READABLE)
-        .expect("Register timer to the epoll()");
-
-    // Initialize kime context
-    let mut kime_ctx = KimeContext::new(vk, im, grab, timer);
-    event_queue
-        .sync_roundtrip(&mut kime_ctx, |_, _, _| ())
-        .unwrap();
+    // App profile is not applied when compositor doesn't support it
+    let toplevel_manager = globals.instantiate_range::<ZwlrForeignToplevelManagerV1>(1, 3);
+    match toplevel_manager {
+        Ok(ref manager) => toplevel::assign_manager(manager),
+        Err(_) => log::info!("Can't load foreign toplevel manager, app profile is disabled"),
+    }
+
+    // Initialize kime context for each seat
+    state.set_globals(SeatGlobals {
+        im_manager,
+        vk_manager,
+        popup,
+    });
+    event_queue.sync_roundtrip(&mut state, |_, _, _| ())?;
+    state.check_unavailable()?;
 
     log::info!("Server init success!");
 
@@ -464,7 +961,7 @@ fn main() {
     // Reference:
     //   https://docs.rs/wayland-client/0.28.3/wayland_client/struct.EventQueue.html
     let mut events = MioEvents::with_capacity(1024);
-    let stop_reason = 'main: loop {
+    loop {
         use std::io::ErrorKind;
 
         // Sleep until next event
@@ -476,18 +973,22 @@ fn main() {
             if e.kind() == ErrorKind::Interrupted {
                 continue;
             }
-            break Err(e);
+            return Err(e);
         }
 
         for event in &events {
             match event.token() {
                 POLL_WAYLAND => {}
-                POLL_TIMER => {
-                    if let Err(e) = kime_ctx.handle_timer_ev() {
-                        break 'main Err(e);
+                POLL_RETRY => state.handle_retry_ev()?,
+                Token(token) => {
+                    if let Some(ctx) = state.contexts.get_mut(&((token / 2) as u32)) {
+                        if token % 2 == 0 {
+                            ctx.handle_timer_ev()?;
+                        } else {
+                            ctx.handle_hold_timer_ev()?;
+                        }
                     }
                 }
-                _ => unreachable!(),
             }
         }
 
@@ -496,26 +997,57 @@ fn main() {
             if let Err(e) = guard.read_events() {
                 // EWOULDBLOCK here means there's no new messages to read
                 if e.kind() != ErrorKind::WouldBlock {
-                    break Err(e);
+                    return Err(e);
                 }
             }
         }
 
-        if let Err(e) = event_queue.dispatch_pending(&mut kime_ctx, |_, _, _| {}) {
-            break Err(e);
-        }
+        event_queue.dispatch_pending(&mut state, |_, _, _| {})?;
+        state.check_unavailable()?;
 
         // Flush pending writes
         if let Err(e) = display.flush() {
             // EWOULDBLOCK here means there're so many to write, retry later
             if e.kind() != ErrorKind::WouldBlock {
-                break Err(e);
+                return Err(e);
             }
         }
-    };
+    }
+}
+
+fn main() {
+    kime_version::cli_boilerplate!((),);
+
+    assert!(
+        kime_engine_cffi::check_api_version(),
+        "Engine version mismatched"
+    );
+
+    let mut display = Display::connect_to_env().expect("Failed to connect wayland display");
+    let mut retry_delay = RETRY_DELAY_MIN;
 
-    match stop_reason {
-        Ok(()) => log::info!("Server finished gracefully"),
-        Err(e) => log::error!("Server aborted due to IO Error: {}", e),
+    // Reconnect when compositor is restarted
+    loop {
+        let started_at = Instant::now();
+
+        if let Err(e) = run(display) {
+            log::error!("Server aborted due to IO Error: {}", e);
+        }
+
+        // Connection was alive long enough, compositor has been restarted
+        if started_at.elapsed() > RETRY_DELAY_MAX {
+            retry_delay = RETRY_DELAY_MIN;
+        }
+
+        display = loop {
+            log::info!("Reconnect wayland display after {:?}", retry_delay);
+            std::thread::sleep(retry_delay);
+            retry_delay = (retry_delay * 2).min(RETRY_DELAY_MAX);
+
+            match Display::connect_to_env() {
+                Ok(display) => break display,
+                Err(e) => log::warn!("Failed to connect wayland display: {}", e),
+            }
+        };
     }
 }
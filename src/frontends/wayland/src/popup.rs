@@ -0,0 +1,180 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::unix::io::{AsRawFd, FromRawFd},
+};
+
+use rusttype::{point, Font, Scale};
+use wayland_client::{
+    protocol::{
+        wl_buffer::{Event as BufferEvent, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_shm::{Format, WlShm},
+        wl_surface::WlSurface,
+    },
+    Main,
+};
+use wayland_protocols::misc::zwp_input_method_v2::client::{
+    zwp_input_method_v2::ZwpInputMethodV2, zwp_input_popup_surface_v2::ZwpInputPopupSurfaceV2,
+};
+
+// ARGB8888 is little endian so byte order is BGRA
+const BACKGROUND: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const HIGHLIGHT: [u8; 4] = [0xf0, 0xd8, 0xb0, 0xff];
+const FOREGROUND: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+
+/// Popup surface which is placed next to text input cursor by compositor
+pub struct Popup {
+    surface: Main<WlSurface>,
+    popup_surface: Main<ZwpInputPopupSurfaceV2>,
+    shm: Main<WlShm>,
+    font: Font<'static>,
+    scale: Scale,
+    visible: bool,
+}
+
+impl Drop for Popup {
+    fn drop(&mut self) {
+        self.popup_surface.destroy();
+        self.surface.destroy();
+    }
+}
+
+impl Popup {
+    pub fn new(
+        compositor: &Main<WlCompositor>,
+        shm: Main<WlShm>,
+        im: &Main<ZwpInputMethodV2>,
+        (font_data, index): (&[u8], u32),
+        font_size: f32,
+    ) -> Option<Self> {
+        let font = Font::try_from_vec_and_index(font_data.to_vec(), index)?;
+        let surface = compositor.create_surface();
+        let popup_surface = im.get_input_popup_surface(&surface);
+
+        Some(Self {
+            surface,
+            popup_surface,
+            shm,
+            font,
+            scale: Scale::uniform(font_size),
+            visible: false,
+        })
+    }
+
+    pub fn hide(&mut self) {
+        if self.visible {
+            self.surface.attach(None, 0, 0);
+            self.surface.commit();
+            self.visible = false;
+        }
+    }
+
+    /// Draw lines of text, `highlight` is index of line which has highlighted background
+    pub fn draw(&mut self, lines: &[String], highlight: Option<usize>) {
+        if lines.is_empty() {
+            self.hide();
+            return;
+        }
+
+        let v_metrics = self.font.v_metrics(self.scale);
+        let line_height =
+            (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil() as usize;
+        let padding = (self.scale.y * 0.3) as usize;
+
+        let text_width = lines
+            .iter()
+            .map(|line| self.line_width(line))
+            .max()
+            .unwrap_or(0);
+        let width = text_width + padding * 2;
+        let height = line_height * lines.len() + padding * 2;
+
+        let mut pixels = vec![0; width * height * 4];
+
+        for (y, row) in pixels.chunks_exact_mut(width * 4).enumerate() {
+            let highlighted = y >= padding && highlight == Some((y - padding) / line_height);
+            let color = if highlighted { HIGHLIGHT } else { BACKGROUND };
+
+            for px in row.chunks_exact_mut(4) {
+                px.copy_from_slice(&color);
+            }
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            let origin = point(
+                padding as f32,
+                (padding + i * line_height) as f32 + v_metrics.ascent,
+            );
+
+            for glyph in self.font.layout(line, self.scale, origin) {
+                let bb = match glyph.pixel_bounding_box() {
+                    Some(bb) => bb,
+                    None => continue,
+                };
+
+                glyph.draw(|gx, gy, v| {
+                    let x = bb.min.x + gx as i32;
+                    let y = bb.min.y + gy as i32;
+
+                    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                        return;
+                    }
+
+                    let offset = (y as usize * width + x as usize) * 4;
+                    let px = &mut pixels[offset..offset + 4];
+
+                    for c in 0..3 {
+                        px[c] = (FOREGROUND[c] as f32 * v + px[c] as f32 * (1.0 - v)) as u8;
+                    }
+                });
+            }
+        }
+
+        match self.create_buffer(width as i32, height as i32, &pixels) {
+            Ok(buffer) => {
+                self.surface.attach(Some(&buffer), 0, 0);
+                self.surface.damage(0, 0, width as i32, height as i32);
+                self.surface.commit();
+                self.visible = true;
+            }
+            Err(e) => {
+                log::error!("Can't create popup buffer: {}", e);
+            }
+        }
+    }
+
+    fn line_width(&self, line: &str) -> usize {
+        self.font
+            .layout(line, self.scale, point(0.0, 0.0))
+            .last()
+            .map_or(0.0, |g| {
+                g.position().x + g.unpositioned().h_metrics().advance_width
+            })
+            .ceil() as usize
+    }
+
+    fn create_buffer(&self, width: i32, height: i32, pixels: &[u8]) -> io::Result<Main<WlBuffer>> {
+        let fd = unsafe { libc::memfd_create(b"kime-popup\0".as_ptr().cast(), libc::MFD_CLOEXEC) };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(pixels)?;
+
+        // fd is duplicated when request is sent so it's safe to close file after this
+        let pool = self.shm.create_pool(file.as_raw_fd(), pixels.len() as i32);
+        let buffer = pool.create_buffer(0, width, height, width * 4, Format::Argb8888);
+        pool.destroy();
+
+        buffer.quick_assign(|buffer, event, _| {
+            if let BufferEvent::Release = event {
+                buffer.destroy();
+            }
+        });
+
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use wayland_client::Main;
+use wayland_protocols::wlr::unstable::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{Event as HandleEvent, State, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{Event as ManagerEvent, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::KimeState;
+
+#[derive(Default)]
+struct Toplevel {
+    app_id: String,
+    activated: bool,
+}
+
+/// Track app id of activated toplevel with wlr foreign toplevel management
+#[derive(Default)]
+pub struct ToplevelTracker {
+    /// Key is protocol id of toplevel handle
+    toplevels: HashMap<u32, Toplevel>,
+    active_app: Option<String>,
+}
+
+impl ToplevelTracker {
+    pub fn active_app(&self) -> Option<&str> {
+        self.active_app.as_deref()
+    }
+
+    /// Handle toplevel event
+    ///
+    /// # Return
+    ///
+    /// New app id when activated app is changed
+    fn handle_event(&mut self, id: u32, ev: HandleEvent) -> Option<String> {
+        match ev {
+            HandleEvent::AppId { app_id } => {
+                self.toplevels.entry(id).or_default().app_id = app_id;
+            }
+            HandleEvent::State { state } => {
+                let activated = state
+                    .chunks_exact(4)
+                    .map(|raw| u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]))
+                    .any(|raw| raw == State::Activated.to_raw());
+                self.toplevels.entry(id).or_default().activated = activated;
+            }
+            HandleEvent::Done => {
+                let toplevel = self.toplevels.get(&id)?;
+
+                if toplevel.activated
+                    && self.active_app.as_deref() != Some(toplevel.app_id.as_str())
+                {
+                    self.active_app = Some(toplevel.app_id.clone());
+                    return self.active_app.clone();
+                }
+            }
+            HandleEvent::Closed => {
+                self.toplevels.remove(&id);
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+/// Forward toplevel events to `KimeState`
+pub fn assign_manager(manager: &Main<ZwlrForeignToplevelManagerV1>) {
+    manager.quick_assign(|_, ev, _| {
+        if let ManagerEvent::Toplevel { toplevel } = ev {
+            toplevel.quick_assign(handle_toplevel_ev);
+        }
+    });
+}
+
+fn handle_toplevel_ev(
+    handle: Main<ZwlrForeignToplevelHandleV1>,
+    ev: HandleEvent,
+    mut data: wayland_client::DispatchData,
+) {
+    let closed = matches!(ev, HandleEvent::Closed);
+    let id = handle.as_ref().id();
+
+    if let Some(state) = data.get::<KimeState>() {
+        if let Some(app) = state.toplevels.handle_event(id, ev) {
+            state.set_active_app(&app);
+        }
+    }
+
+    if closed {
+        handle.destroy();
+    }
+}
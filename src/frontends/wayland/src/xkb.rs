@@ -0,0 +1,145 @@
+use std::{io, os::unix::io::RawFd};
+
+use xkbcommon::xkb;
+
+use kime_engine_cffi::{
+    ModifierState, ModifierState_ALT, ModifierState_ALTGR, ModifierState_CAPSLOCK,
+    ModifierState_CONTROL, ModifierState_NUMLOCK, ModifierState_SHIFT, ModifierState_SUPER,
+};
+
+// Same names as `XKB_VMOD_NAME_*` in xkbcommon-names.h
+const VMOD_NAME_ALT: &str = "Alt";
+const VMOD_NAME_SUPER: &str = "Super";
+const VMOD_NAME_LEVEL3: &str = "LevelThree";
+const VMOD_NAME_NUM: &str = "NumLock";
+
+/// Read keymap string which is sent by `wl_keyboard.keymap`
+pub fn read_keymap(fd: RawFd, size: u32) -> io::Result<Vec<u8>> {
+    let size = size as usize;
+
+    unsafe {
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            fd,
+            0,
+        );
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        let data = std::slice::from_raw_parts(ptr as *const u8, size).to_vec();
+        libc::munmap(ptr, size);
+
+        Ok(data)
+    }
+}
+
+/// Usual real modifier masks which are used when keymap can't be compiled
+const FALLBACK_MASKS: [(u32, ModifierState); 7] = [
+    (0x1, ModifierState_SHIFT),
+    (0x2, ModifierState_CAPSLOCK),
+    (0x4, ModifierState_CONTROL),
+    (0x8, ModifierState_ALT),
+    (0x10, ModifierState_NUMLOCK),
+    (0x40, ModifierState_SUPER),
+    (0x80, ModifierState_ALTGR),
+];
+
+/// Modifier state from serialized modifiers without keymap
+pub fn fallback_modifier_state(depressed: u32, latched: u32, locked: u32) -> ModifierState {
+    let mods = depressed | latched | locked;
+
+    FALLBACK_MASKS
+        .iter()
+        .filter(|(mask, _)| mods & mask != 0)
+        .fold(0, |state, (_, modifier)| state | modifier)
+}
+
+/// Compiled xkb keymap with modifier indices resolved by name
+pub struct Keymap {
+    state: xkb::State,
+    modifiers: [(xkb::ModIndex, ModifierState); 7],
+}
+
+impl Keymap {
+    pub fn new(data: &[u8]) -> Option<Self> {
+        // Keymap string is null terminated
+        let data = data.split(|b| *b == 0).next().unwrap_or(data);
+        let data = String::from_utf8(data.to_vec()).ok()?;
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_string(
+            &context,
+            data,
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )?;
+
+        // Prefer virtual modifiers since real modifier they are mapped to depends on keymap
+        let index = |vmod: &str, fallback: &str| match keymap.mod_get_index(vmod) {
+            xkb::MOD_INVALID => keymap.mod_get_index(fallback),
+            index => index,
+        };
+
+        let modifiers = [
+            (
+                keymap.mod_get_index(xkb::MOD_NAME_SHIFT),
+                ModifierState_SHIFT,
+            ),
+            (
+                keymap.mod_get_index(xkb::MOD_NAME_CTRL),
+                ModifierState_CONTROL,
+            ),
+            (index(VMOD_NAME_ALT, xkb::MOD_NAME_ALT), ModifierState_ALT),
+            (
+                index(VMOD_NAME_SUPER, xkb::MOD_NAME_LOGO),
+                ModifierState_SUPER,
+            ),
+            (
+                index(VMOD_NAME_LEVEL3, xkb::MOD_NAME_ISO_LEVEL3_SHIFT),
+                ModifierState_ALTGR,
+            ),
+            (
+                keymap.mod_get_index(xkb::MOD_NAME_CAPS),
+                ModifierState_CAPSLOCK,
+            ),
+            (
+                index(VMOD_NAME_NUM, xkb::MOD_NAME_NUM),
+                ModifierState_NUMLOCK,
+            ),
+        ];
+
+        Some(Self {
+            state: xkb::State::new(&keymap),
+            modifiers,
+        })
+    }
+
+    pub fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        self.state
+            .update_mask(depressed, latched, locked, 0, 0, group);
+    }
+
+    fn is_active(&self, index: xkb::ModIndex) -> bool {
+        index != xkb::MOD_INVALID
+            && self
+                .state
+                .mod_index_is_active(index, xkb::STATE_MODS_EFFECTIVE)
+    }
+
+    pub fn modifier_state(&self) -> ModifierState {
+        self.modifiers
+            .iter()
+            .filter(|(index, _)| self.is_active(*index))
+            .fold(0, |state, (_, modifier)| state | modifier)
+    }
+
+    /// Keysym of xkb keycode with current modifiers
+    pub fn keysym(&self, keycode: u32) -> u32 {
+        self.state.key_get_one_sym(keycode.into()).raw()
+    }
+}
@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+
+use kime_engine_backend_hangul::{is_ksx1001_locale, ksx1001_syllable_code};
+
+/// Encoding of strings sent to XIM client
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientEncoding {
+    Utf8,
+    /// EUC-KR and CP949 locales which only can decode KS X 1001
+    EucKr,
+}
+
+impl Default for ClientEncoding {
+    fn default() -> Self {
+        Self::Utf8
+    }
+}
+
+const CHO: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+const JONG: [Option<char>; 28] = [
+    None,
+    Some('ㄱ'),
+    Some('ㄲ'),
+    Some('ㄳ'),
+    Some('ㄴ'),
+    Some('ㄵ'),
+    Some('ㄶ'),
+    Some('ㄷ'),
+    Some('ㄹ'),
+    Some('ㄺ'),
+    Some('ㄻ'),
+    Some('ㄼ'),
+    Some('ㄽ'),
+    Some('ㄾ'),
+    Some('ㄿ'),
+    Some('ㅀ'),
+    Some('ㅁ'),
+    Some('ㅂ'),
+    Some('ㅄ'),
+    Some('ㅅ'),
+    Some('ㅆ'),
+    Some('ㅇ'),
+    Some('ㅈ'),
+    Some('ㅊ'),
+    Some('ㅋ'),
+    Some('ㅌ'),
+    Some('ㅍ'),
+    Some('ㅎ'),
+];
+
+/// Encode char into KS X 1001 (EUC-KR without UHC extension)
+fn encode_ksc(c: char) -> Option<[u8; 2]> {
+    match c {
+        '가'..='힣' => ksx1001_syllable_code(c),
+        // Row 4 has every compatibility jamos in same order
+        'ㄱ'..='ㆎ' => Some([0xA4, 0xA1 + (c as u32 - 'ㄱ' as u32) as u8]),
+        // Symbols and hanja
+        _ => {
+            let mut buf = [0; 4];
+            let (bytes, _, unmappable) = encoding_rs::EUC_KR.encode(c.encode_utf8(&mut buf));
+
+            match *bytes {
+                [hi @ 0xA1..=0xFE, lo @ 0xA1..=0xFE] if !unmappable => Some([hi, lo]),
+                _ => None,
+            }
+        }
+    }
+}
+
+impl ClientEncoding {
+    /// Guess encoding from locale name e.g. `ko_KR.EUC-KR`
+    pub fn from_locale(locale: &str) -> Self {
+        if is_ksx1001_locale(locale) {
+            Self::EucKr
+        } else {
+            Self::Utf8
+        }
+    }
+
+    /// Replace chars client can't decode
+    ///
+    /// Hangul syllables outside of KS X 1001 are decomposed into compatibility jamos
+    pub fn fallback(self, s: &str) -> Cow<'_, str> {
+        if self == Self::Utf8 || s.chars().all(|c| c.is_ascii() || encode_ksc(c).is_some()) {
+            return Cow::Borrowed(s);
+        }
+
+        let mut ret = String::with_capacity(s.len());
+
+        for c in s.chars() {
+            if c.is_ascii() || encode_ksc(c).is_some() {
+                ret.push(c);
+            } else if let '가'..='힣' = c {
+                let idx = c as u32 - '가' as u32;
+                ret.push(CHO[(idx / 588) as usize]);
+                // Compatibility jungseongs are contiguous from ㅏ
+                ret.extend(char::from_u32('ㅏ' as u32 + idx % 588 / 28));
+                ret.extend(JONG[(idx % 28) as usize]);
+            } else {
+                ret.push('?');
+            }
+        }
+
+        Cow::Owned(ret)
+    }
+
+    /// Convert string into compound text
+    ///
+    /// Chars are expected to be passed through [`ClientEncoding::fallback`] first
+    pub fn compound_text(self, s: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => xim_ctext::utf8_to_compound_text(s),
+            Self::EucKr => {
+                let mut ret = Vec::with_capacity(s.len() + 4);
+                let mut designated = false;
+
+                for c in s.chars() {
+                    if c.is_ascii() {
+                        ret.push(c as u8);
+                    } else if let Some(bytes) = encode_ksc(c) {
+                        if !designated {
+                            // Designate KSC5601.1987-0 to GR
+                            ret.extend_from_slice(b"\x1b$)C");
+                            designated = true;
+                        }
+                        ret.extend_from_slice(&bytes);
+                    } else {
+                        ret.push(b'?');
+                    }
+                }
+
+                ret
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientEncoding;
+
+    #[test]
+    fn from_locale() {
+        assert_eq!(
+            ClientEncoding::from_locale("ko_KR.UTF-8"),
+            ClientEncoding::Utf8
+        );
+        assert_eq!(
+            ClientEncoding::from_locale("en_US.UTF-8"),
+            ClientEncoding::Utf8
+        );
+        assert_eq!(ClientEncoding::from_locale("C"), ClientEncoding::Utf8);
+        assert_eq!(
+            ClientEncoding::from_locale("ko_KR.EUC-KR"),
+            ClientEncoding::EucKr
+        );
+        assert_eq!(
+            ClientEncoding::from_locale("ko_KR.eucKR"),
+            ClientEncoding::EucKr
+        );
+        assert_eq!(
+            ClientEncoding::from_locale("ko_KR.CP949"),
+            ClientEncoding::EucKr
+        );
+        assert_eq!(
+            ClientEncoding::from_locale("ko_KR.UHC@euro"),
+            ClientEncoding::EucKr
+        );
+        assert_eq!(ClientEncoding::from_locale("ko_KR"), ClientEncoding::EucKr);
+        assert_eq!(ClientEncoding::from_locale("kok_IN"), ClientEncoding::Utf8);
+    }
+
+    #[test]
+    fn fallback() {
+        assert_eq!(ClientEncoding::Utf8.fallback("똠"), "똠");
+        assert_eq!(ClientEncoding::EucKr.fallback("abc 가나"), "abc 가나");
+        // 똠 and 갃 aren't in KS X 1001
+        assert_eq!(ClientEncoding::EucKr.fallback("똠방각하"), "ㄸㅗㅁ방각하");
+        assert_eq!(ClientEncoding::EucKr.fallback("갃"), "ㄱㅏㄳ");
+        assert_eq!(ClientEncoding::EucKr.fallback("😀"), "?");
+    }
+
+    #[test]
+    fn compound_text() {
+        assert_eq!(ClientEncoding::EucKr.compound_text("a"), b"a");
+        assert_eq!(
+            ClientEncoding::EucKr.compound_text("a가힝ㄱ漢"),
+            b"a\x1b$)C\xb0\xa1\xc8\xfe\xa4\xa1\xf9\xd3"
+        );
+        assert_eq!(ClientEncoding::EucKr.compound_text("😀"), b"?");
+    }
+
+    #[test]
+    fn ksx1001_matches_encoding_rs() {
+        for c in ('가'..='힣').chain('ㄱ'..='ㆎ') {
+            let mut buf = [0; 4];
+            let (bytes, _, unmappable) = encoding_rs::EUC_KR.encode(c.encode_utf8(&mut buf));
+            let expected = match *bytes {
+                [hi @ 0xA1..=0xFE, lo @ 0xA1..=0xFE] if !unmappable => Some([hi, lo]),
+                _ => None,
+            };
+
+            assert_eq!(super::encode_ksc(c), expected, "{:?}", c);
+        }
+    }
+}
@@ -1,23 +1,76 @@
-use std::{num::NonZeroU32, sync::Arc};
+use std::{
+    num::NonZeroU32,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use crate::pe_window::PeWindow;
+use crate::ctext::ClientEncoding;
+use crate::keysym::KeyboardMapping;
+use crate::pe_window::{FontSet, PeWindow};
 use ahash::AHashMap;
 use x11rb::{
     connection::Connection,
-    protocol::xproto::{ConfigureNotifyEvent, KeyButMask, KeyPressEvent, KEY_PRESS_EVENT},
+    protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConfigureNotifyEvent, ConnectionExt as _, KeyButMask,
+        KeyPressEvent, PropMode, CLIENT_MESSAGE_EVENT, KEY_PRESS_EVENT,
+    },
+    wrapper::ConnectionExt as _,
 };
 use xim::{
     x11rb::{HasConnection, X11rbServer},
-    InputStyle, Server, ServerHandler,
+    CommitData, Feedback, InputStyle, PreeditDrawStatus, Request, Server, ServerCore,
+    ServerHandler, StatusContent, StatusTextContent, XimWrite as _,
 };
 
 use kime_engine_cffi::*;
 
+/// Key which is held by engine for sequence or chord hotkey
+struct HeldKey {
+    press: KeyPressEvent,
+    /// Release is forwarded with press when key is replayed
+    release: Option<KeyPressEvent>,
+}
+
+/// Keycode of forward event which flushes held keys, X server never sends it
+const FLUSH_KEYCODE: u8 = 0;
+
+/// Input context which holds keys, its held keys are flushed by event loop at deadline
+struct HeldIc {
+    com_win: u32,
+    input_method_id: u16,
+    input_context_id: u16,
+    deadline: Instant,
+}
+
+impl HeldIc {
+    fn is_ic(&self, com_win: u32, ic: &xim::InputContext) -> bool {
+        self.com_win == com_win
+            && self.input_method_id == ic.input_method_id().get()
+            && self.input_context_id == ic.input_context_id().get()
+    }
+}
+
 pub struct KimeData {
     engine: InputEngine,
     pe: Option<NonZeroU32>,
+    /// Status window for `STATUS_AREA`
+    status_window: Option<NonZeroU32>,
+    /// Status text currently shown
+    status: Option<&'static str>,
+    /// Preedit is started for `PREEDIT_CALLBACKS`
+    preedit_started: bool,
+    /// Char length of preedit which is drawn in client
+    preedit_len: usize,
+    /// WM_CLASS of client which is used for app profile
+    app: Option<String>,
+    /// Encoding of client locale
+    encoding: ClientEncoding,
     show_preedit_window: bool,
     engine_ready: bool,
+    /// `StatusAttributes.Area` of IC which is relative to client window
+    status_area: Option<xim::Rectangle>,
+    /// Keys which are not forwarded to client yet
+    held_keys: Vec<HeldKey>,
 }
 
 impl KimeData {
@@ -25,48 +78,299 @@ impl KimeData {
         Self {
             engine: InputEngine::new(config),
             pe: None,
+            status_window: None,
+            status: None,
+            preedit_started: false,
+            preedit_len: 0,
+            app: None,
+            encoding: ClientEncoding::default(),
             show_preedit_window,
             engine_ready: true,
+            status_area: None,
+            held_keys: Vec::new(),
+        }
+    }
+}
+
+/// Send key event to client which is not processed by input method
+fn forward_event<C: HasConnection>(
+    server: &mut X11rbServer<C>,
+    user_ic: &xim::UserInputContext<KimeData>,
+    xev: &KeyPressEvent,
+) -> Result<(), xim::ServerError> {
+    server.send_req(
+        user_ic.ic.client_win(),
+        Request::ForwardEvent {
+            input_method_id: user_ic.ic.input_method_id().get(),
+            input_context_id: user_ic.ic.input_context_id().get(),
+            serial_number: 0,
+            flag: xim::ForwardEventFlag::empty(),
+            xev: xim::XEvent {
+                response_type: xev.response_type,
+                detail: xev.detail,
+                sequence: xev.sequence,
+                time: xev.time,
+                root: xev.root,
+                event: xev.event,
+                child: xev.child,
+                root_x: xev.root_x,
+                root_y: xev.root_y,
+                event_x: xev.event_x,
+                event_y: xev.event_y,
+                state: xev.state.into(),
+                same_screen: xev.same_screen,
+            },
+        },
+    )
+}
+
+/// Find `Area` in nested `StatusAttributes` list
+fn find_status_area(ic_attributes: &[xim::Attribute]) -> Option<xim::Rectangle> {
+    let attr = ic_attributes
+        .iter()
+        .find(|attr| xim::attrs::get_name(attr.id) == Some(xim::AttributeName::StatusAttributes))?;
+
+    let mut b = &attr.value[..];
+    let mut area = None;
+
+    while let Ok(attr) = xim::read::<xim::Attribute>(b) {
+        b = &b[attr.size()..];
+
+        if xim::attrs::get_name(attr.id) == Some(xim::AttributeName::Area) {
+            area = xim::read(&attr.value).ok();
+        }
+    }
+
+    area
+}
+
+/// Read `StatusAttributes.Area` of `CreateIc` and `SetIcValues` request
+///
+/// xim crate ignores status attributes so request is parsed again before server consumes it
+pub fn read_status_area(
+    conn: &impl Connection,
+    msg: &ClientMessageEvent,
+) -> Result<Option<StatusArea>, xim::ServerError> {
+    let req = if msg.format == 32 {
+        let [length, atom, ..] = msg.data.as_data32();
+        // Don't delete property which will be read by server
+        let data = conn
+            .get_property(false, msg.window, atom, AtomEnum::ANY, 0, length)?
+            .reply()?
+            .value;
+        xim::read::<Request>(&data)
+    } else {
+        xim::read::<Request>(&msg.data.as_data8())
+    };
+
+    Ok(match req {
+        Ok(Request::CreateIc { ic_attributes, .. }) => {
+            find_status_area(&ic_attributes).map(|area| StatusArea {
+                input_context_id: None,
+                area,
+            })
+        }
+        Ok(Request::SetIcValues {
+            input_context_id,
+            ic_attributes,
+            ..
+        }) => find_status_area(&ic_attributes).map(|area| StatusArea {
+            input_context_id: Some(input_context_id),
+            area,
+        }),
+        _ => None,
+    })
+}
+
+/// Status area of request which is being handled by server
+pub struct StatusArea {
+    /// `None` for `CreateIc`
+    input_context_id: Option<u16>,
+    area: xim::Rectangle,
+}
+
+/// Find class name in WM_CLASS of window or its ancestors
+fn find_wm_class(conn: &impl Connection, mut window: u32) -> Result<String, xim::ServerError> {
+    loop {
+        let class = conn
+            .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 1024)?
+            .reply()?;
+
+        // WM_CLASS is `instance\0class\0`
+        if let Some(class) = class.value.split(|b| *b == 0).nth(1) {
+            return Ok(String::from_utf8_lossy(class).into_owned());
+        }
+
+        let tree = conn.query_tree(window)?.reply()?;
+
+        if tree.parent == x11rb::NONE || tree.parent == tree.root {
+            return Ok(String::new());
         }
+
+        window = tree.parent;
+    }
+}
+
+fn status_text(engine: &InputEngine) -> &'static str {
+    match engine.input_mode() {
+        Some(InputMode::Math) => "Math",
+        Some(InputMode::Hanja) => "Hanja",
+        Some(InputMode::Emoji) => "Emoji",
+        None => match engine.input_category() {
+            InputCategory::Latin => "Latin",
+            InputCategory::Hangul => "Hangul",
+        },
     }
 }
 
 pub struct KimeHandler {
+    /// Preedit windows and status windows
     preedit_windows: AHashMap<NonZeroU32, PeWindow>,
-    font: (Arc<rusttype::Font<'static>>, f32),
-    config: Config,
+    fonts: FontSet,
+    /// Font size in pixel which is scaled by dpi
+    font_size: f32,
+    config: Rc<Config>,
     screen_num: usize,
+    /// Loaded on demand for `KeyMatching::Keysym`
+    keyboard_mapping: Option<KeyboardMapping>,
+    /// Status area of current request
+    status_area: Option<StatusArea>,
+    /// Communication window of current request
+    request_window: u32,
+    held_ic: Option<HeldIc>,
 }
 
 impl KimeHandler {
-    pub fn new(screen_num: usize, config: Config) -> Self {
-        let (font_data, index, font_size) = config.xim_font();
-        let font = Arc::new(
-            rusttype::Font::try_from_vec_and_index(font_data.to_vec(), index)
-                .unwrap()
-                .to_owned(),
-        );
+    pub fn new(screen_num: usize, config: Config, scale: f32) -> Self {
+        let config = Rc::new(config);
+        let (_, _, font_size) = config.xim_font();
+        let fonts = FontSet::new(config.clone());
 
         Self {
             preedit_windows: AHashMap::new(),
             config,
             screen_num,
-            font: (font, font_size),
+            fonts,
+            font_size: font_size * scale,
+            keyboard_mapping: None,
+            status_area: None,
+            request_window: 0,
+            held_ic: None,
         }
     }
 }
 
 impl KimeHandler {
+    /// Remember status area of request before server handles it
+    pub fn set_status_area(&mut self, status_area: Option<StatusArea>) {
+        self.status_area = status_area;
+    }
+
+    /// Remember communication window of request before server handles it
+    pub fn set_request_window(&mut self, window: u32) {
+        self.request_window = window;
+    }
+
+    /// Time until held keys of input context should be flushed
+    pub fn held_timeout(&self) -> Option<Duration> {
+        self.held_ic
+            .as_ref()
+            .map(|held| held.deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Make `ForwardEvent` request which flushes held keys of input context
+    ///
+    /// Input context is only accessible in request handler so request is handled by server like
+    /// one from client
+    pub fn flush_request(
+        &mut self,
+        conn: &impl Connection,
+        xim_protocol: Atom,
+    ) -> Result<Option<ClientMessageEvent>, xim::ServerError> {
+        let held = match self.held_ic.take() {
+            Some(held) => held,
+            None => return Ok(None),
+        };
+
+        let req = Request::ForwardEvent {
+            input_method_id: held.input_method_id,
+            input_context_id: held.input_context_id,
+            serial_number: 0,
+            flag: xim::ForwardEventFlag::empty(),
+            xev: xim::XEvent {
+                response_type: KEY_PRESS_EVENT,
+                detail: FLUSH_KEYCODE,
+                sequence: 0,
+                time: 0,
+                root: 0,
+                event: 0,
+                child: 0,
+                root_x: 0,
+                root_y: 0,
+                event_x: 0,
+                event_y: 0,
+                state: 0,
+                same_screen: false,
+            },
+        };
+        let data = xim::write_to_vec(req);
+
+        conn.change_property8(
+            PropMode::REPLACE,
+            held.com_win,
+            xim_protocol,
+            AtomEnum::STRING,
+            &data,
+        )?;
+
+        Ok(Some(ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window: held.com_win,
+            type_: xim_protocol,
+            data: [data.len() as u32, xim_protocol, 0, 0, 0].into(),
+        }))
+    }
+
+    /// Track deadline of held keys after engine handles key
+    fn update_held_ic(&mut self, user_ic: &xim::UserInputContext<KimeData>) {
+        match user_ic.user_data.engine.held_timeout(&self.config) {
+            Some(timeout) => {
+                self.held_ic = Some(HeldIc {
+                    com_win: self.request_window,
+                    input_method_id: user_ic.ic.input_method_id().get(),
+                    input_context_id: user_ic.ic.input_context_id().get(),
+                    deadline: Instant::now() + timeout,
+                });
+            }
+            None => {
+                if self
+                    .held_ic
+                    .as_ref()
+                    .map_or(false, |held| held.is_ic(self.request_window, &user_ic.ic))
+                {
+                    self.held_ic = None;
+                }
+            }
+        }
+    }
+
     pub fn expose(&mut self, window: u32, conn: &impl Connection) -> Result<(), xim::ServerError> {
         if let Some(win) = NonZeroU32::new(window) {
             if let Some(pe) = self.preedit_windows.get_mut(&win) {
-                pe.expose(conn)?;
+                pe.expose(conn, &mut self.fonts)?;
             }
         }
 
         Ok(())
     }
 
+    /// Keyboard mapping is changed, reload it on next key press
+    pub fn mapping_notify(&mut self) {
+        self.keyboard_mapping = None;
+    }
+
     pub fn configure_notify(
         &mut self,
         e: ConfigureNotifyEvent,
@@ -74,7 +378,7 @@ impl KimeHandler {
     ) -> Result<(), xim::ServerError> {
         if let Some(win) = NonZeroU32::new(e.window) {
             if let Some(pe) = self.preedit_windows.get_mut(&win) {
-                pe.configure_notify(e, conn)?;
+                pe.configure_notify(e, conn, &mut self.fonts)?;
             }
         }
 
@@ -86,7 +390,97 @@ impl KimeHandler {
         server: &mut X11rbServer<C>,
         ic: &mut xim::UserInputContext<KimeData>,
     ) -> Result<(), xim::ServerError> {
-        server.preedit_draw(&mut ic.ic, ic.user_data.engine.preedit_str())?;
+        let composing_start = ic.user_data.engine.preedit_composing_start();
+        let preedit = ic.user_data.engine.preedit_str();
+
+        if preedit.is_empty() {
+            return self.preedit_done(server, ic);
+        }
+
+        // Fallback each part separately so that feedbacks still match
+        let encoding = ic.user_data.encoding;
+        let (composed, composing) = preedit.split_at(composing_start.min(preedit.len()));
+        let composed = encoding.fallback(composed);
+        let composing = encoding.fallback(composing);
+        let preedit = format!("{}{}", composed, composing);
+
+        // Composed part is underlined and composing character is reversed
+        let composed_len = composed.chars().count();
+        let preedit_len = preedit.chars().count();
+        let feedbacks = (0..preedit_len)
+            .map(|i| {
+                if i < composed_len {
+                    Feedback::Underline
+                } else {
+                    Feedback::Reverse
+                }
+            })
+            .collect();
+        let preedit_string = encoding.compound_text(&preedit);
+
+        if !ic.user_data.preedit_started {
+            server.send_req(
+                ic.ic.client_win(),
+                Request::PreeditStart {
+                    input_method_id: ic.ic.input_method_id().get(),
+                    input_context_id: ic.ic.input_context_id().get(),
+                },
+            )?;
+            ic.user_data.preedit_started = true;
+        }
+
+        server.send_req(
+            ic.ic.client_win(),
+            Request::PreeditDraw {
+                input_method_id: ic.ic.input_method_id().get(),
+                input_context_id: ic.ic.input_context_id().get(),
+                chg_first: 0,
+                chg_length: ic.user_data.preedit_len as _,
+                caret: preedit_len as _,
+                preedit_string,
+                feedbacks,
+                status: PreeditDrawStatus::empty(),
+            },
+        )?;
+
+        ic.user_data.preedit_len = preedit_len;
+
+        Ok(())
+    }
+
+    fn preedit_done<C: HasConnection>(
+        &mut self,
+        server: &mut X11rbServer<C>,
+        ic: &mut xim::UserInputContext<KimeData>,
+    ) -> Result<(), xim::ServerError> {
+        if !ic.user_data.preedit_started {
+            return Ok(());
+        }
+
+        server.send_req(
+            ic.ic.client_win(),
+            Request::PreeditDraw {
+                input_method_id: ic.ic.input_method_id().get(),
+                input_context_id: ic.ic.input_context_id().get(),
+                chg_first: 0,
+                chg_length: ic.user_data.preedit_len as _,
+                caret: 0,
+                preedit_string: Vec::new(),
+                feedbacks: Vec::new(),
+                status: PreeditDrawStatus::NO_FEEDBACK | PreeditDrawStatus::NO_STRING,
+            },
+        )?;
+        server.send_req(
+            ic.ic.client_win(),
+            Request::PreeditDone {
+                input_method_id: ic.ic.input_method_id().get(),
+                input_context_id: ic.ic.input_context_id().get(),
+            },
+        )?;
+
+        ic.user_data.preedit_started = false;
+        ic.user_data.preedit_len = 0;
+
         Ok(())
     }
 
@@ -115,19 +509,27 @@ impl KimeHandler {
         if let Some(pe) = user_ic.user_data.pe.as_mut() {
             // Draw in server (already have pe_window)
             let pe = self.preedit_windows.get_mut(pe).unwrap();
-            pe.set_preedit(user_ic.user_data.engine.preedit_str());
+            pe.set_preedit(
+                server.conn(),
+                &mut self.fonts,
+                user_ic.user_data.engine.preedit_str(),
+            )?;
             pe.refresh(server.conn())?;
         } else {
             // Draw in server
             let mut pe = PeWindow::new(
                 server.conn(),
-                self.font.clone(),
+                self.font_size,
                 user_ic.ic.app_win(),
                 user_ic.ic.preedit_spot(),
                 self.screen_num,
             )?;
 
-            pe.set_preedit(user_ic.user_data.engine.preedit_str());
+            pe.set_preedit(
+                server.conn(),
+                &mut self.fonts,
+                user_ic.user_data.engine.preedit_str(),
+            )?;
             user_ic.user_data.pe = Some(pe.window());
 
             self.preedit_windows.insert(pe.window(), pe);
@@ -136,6 +538,125 @@ impl KimeHandler {
         Ok(())
     }
 
+    fn show_status<C: HasConnection>(
+        &mut self,
+        server: &mut X11rbServer<C>,
+        user_ic: &mut xim::UserInputContext<KimeData>,
+    ) -> Result<(), xim::ServerError> {
+        let style = user_ic.ic.input_style();
+
+        if style.contains(InputStyle::STATUS_CALLBACKS) {
+            if user_ic.user_data.status.is_none() {
+                server.send_req(
+                    user_ic.ic.client_win(),
+                    Request::StatusStart {
+                        input_method_id: user_ic.ic.input_method_id().get(),
+                        input_context_id: user_ic.ic.input_context_id().get(),
+                    },
+                )?;
+            }
+        } else if style.contains(InputStyle::STATUS_AREA) {
+            if user_ic.user_data.status_window.is_some() {
+                return Ok(());
+            }
+
+            let spot = match (user_ic.user_data.status_area.as_ref(), user_ic.ic.app_win()) {
+                (Some(area), _) => xim::Point {
+                    x: area.x,
+                    y: area.y,
+                },
+                // Place status window at bottom-left corner of client window when client doesn't
+                // give status area
+                (None, Some(app_win)) => xim::Point {
+                    x: 0,
+                    y: server.conn().get_geometry(app_win.get())?.reply()?.height as i16,
+                },
+                (None, None) => xim::Point { x: 0, y: 0 },
+            };
+
+            let pe = PeWindow::new(
+                server.conn(),
+                self.font_size,
+                user_ic.ic.app_win(),
+                spot,
+                self.screen_num,
+            )?;
+
+            user_ic.user_data.status_window = Some(pe.window());
+            self.preedit_windows.insert(pe.window(), pe);
+        } else {
+            return Ok(());
+        }
+
+        user_ic.user_data.status = None;
+        self.update_status(server, user_ic)
+    }
+
+    fn update_status<C: HasConnection>(
+        &mut self,
+        server: &mut X11rbServer<C>,
+        user_ic: &mut xim::UserInputContext<KimeData>,
+    ) -> Result<(), xim::ServerError> {
+        let text = status_text(&user_ic.user_data.engine);
+
+        if user_ic.user_data.status == Some(text) {
+            return Ok(());
+        }
+
+        if let Some(status_window) = user_ic.user_data.status_window {
+            let pe = self.preedit_windows.get_mut(&status_window).unwrap();
+            pe.set_preedit(server.conn(), &mut self.fonts, text)?;
+            pe.refresh(server.conn())?;
+        } else if user_ic
+            .ic
+            .input_style()
+            .contains(InputStyle::STATUS_CALLBACKS)
+        {
+            server.send_req(
+                user_ic.ic.client_win(),
+                Request::StatusDraw {
+                    input_method_id: user_ic.ic.input_method_id().get(),
+                    input_context_id: user_ic.ic.input_context_id().get(),
+                    content: StatusContent::Text(StatusTextContent {
+                        status: PreeditDrawStatus::NO_FEEDBACK,
+                        status_string: text.into(),
+                        feedbacks: Vec::new(),
+                    }),
+                },
+            )?;
+        } else {
+            return Ok(());
+        }
+
+        user_ic.user_data.status = Some(text);
+
+        Ok(())
+    }
+
+    fn hide_status<C: HasConnection>(
+        &mut self,
+        server: &mut X11rbServer<C>,
+        user_ic: &mut xim::UserInputContext<KimeData>,
+    ) -> Result<(), xim::ServerError> {
+        if let Some(status_window) = user_ic.user_data.status_window.take() {
+            if let Some(w) = self.preedit_windows.remove(&status_window) {
+                w.clean(server.conn())?;
+            }
+        } else if user_ic.user_data.status.is_some() {
+            server.send_req(
+                user_ic.ic.client_win(),
+                Request::StatusDone {
+                    input_method_id: user_ic.ic.input_method_id().get(),
+                    input_context_id: user_ic.ic.input_context_id().get(),
+                },
+            )?;
+        }
+
+        user_ic.user_data.status = None;
+
+        Ok(())
+    }
+
     fn reset<C: HasConnection>(
         &mut self,
         server: &mut X11rbServer<C>,
@@ -147,6 +668,27 @@ impl KimeHandler {
         self.commit(server, user_ic)?;
 
         user_ic.user_data.engine.reset();
+        user_ic.user_data.held_keys.clear();
+        self.update_held_ic(user_ic);
+
+        Ok(())
+    }
+
+    /// Process held keys again and forward them to client if engine doesn't consume them
+    fn replay_held_keys<C: HasConnection>(
+        &mut self,
+        server: &mut X11rbServer<C>,
+        user_ic: &mut xim::UserInputContext<KimeData>,
+    ) -> Result<(), xim::ServerError> {
+        for held in std::mem::take(&mut user_ic.user_data.held_keys) {
+            if !self.handle_forward_event(server, user_ic, &held.press)? {
+                forward_event(server, user_ic, &held.press)?;
+
+                if let Some(release) = held.release {
+                    forward_event(server, user_ic, &release)?;
+                }
+            }
+        }
 
         Ok(())
     }
@@ -178,6 +720,11 @@ impl KimeHandler {
 
         user_ic.user_data.engine_ready = ret & InputResult_NOT_READY == 0;
 
+        // Category or mode could be changed
+        if user_ic.user_data.status.is_some() {
+            self.update_status(server, user_ic)?;
+        }
+
         Ok(ret & InputResult_CONSUMED != 0)
     }
 
@@ -191,8 +738,7 @@ impl KimeHandler {
             .input_style()
             .contains(InputStyle::PREEDIT_CALLBACKS)
         {
-            server.preedit_draw(&mut user_ic.ic, "")?;
-            return Ok(());
+            return self.preedit_done(server, user_ic);
         }
 
         if let Some(pe) = user_ic.user_data.pe.take() {
@@ -212,9 +758,20 @@ impl KimeHandler {
         user_ic: &mut xim::UserInputContext<KimeData>,
     ) -> Result<(), xim::ServerError> {
         self.clear_preedit(server, user_ic)?;
-        let s = user_ic.user_data.engine.commit_str();
+        let encoding = user_ic.user_data.encoding;
+        let s = encoding.fallback(user_ic.user_data.engine.commit_str());
         if !s.is_empty() {
-            server.commit(&user_ic.ic, s)?;
+            server.send_req(
+                user_ic.ic.client_win(),
+                Request::Commit {
+                    input_method_id: user_ic.ic.input_method_id().get(),
+                    input_context_id: user_ic.ic.input_context_id().get(),
+                    data: CommitData::Chars {
+                        commited: encoding.compound_text(&s),
+                        syncronous: false,
+                    },
+                },
+            )?;
         }
         Ok(())
     }
@@ -224,7 +781,7 @@ impl KimeHandler {
 const EVENT_MASK: u32 = 3;
 
 impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
-    type InputStyleArray = [InputStyle; 6];
+    type InputStyleArray = [InputStyle; 9];
     type InputContextData = KimeData;
 
     fn new_ic_data(
@@ -254,10 +811,13 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
     fn input_styles(&self) -> Self::InputStyleArray {
         [
             InputStyle::PREEDIT_NOTHING | InputStyle::STATUS_NOTHING,
+            InputStyle::PREEDIT_NOTHING | InputStyle::STATUS_CALLBACKS,
             InputStyle::PREEDIT_POSITION | InputStyle::STATUS_NONE,
             InputStyle::PREEDIT_POSITION | InputStyle::STATUS_NOTHING,
+            InputStyle::PREEDIT_POSITION | InputStyle::STATUS_AREA,
             InputStyle::PREEDIT_POSITION | InputStyle::STATUS_CALLBACKS,
             InputStyle::PREEDIT_CALLBACKS | InputStyle::STATUS_NOTHING,
+            InputStyle::PREEDIT_CALLBACKS | InputStyle::STATUS_AREA,
             InputStyle::PREEDIT_CALLBACKS | InputStyle::STATUS_CALLBACKS,
         ]
     }
@@ -280,6 +840,21 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
         self.clear_preedit(server, user_ic)?;
         self.preedit(server, user_ic)?;
 
+        let input_context_id = user_ic.ic.input_context_id().get();
+
+        if let Some(status_area) = self.status_area.take() {
+            if status_area.input_context_id == Some(input_context_id) {
+                log::debug!("status area: {:?}", status_area.area);
+                user_ic.user_data.status_area = Some(status_area.area);
+
+                // Move status window into new area
+                if user_ic.user_data.status_window.is_some() {
+                    self.hide_status(server, user_ic)?;
+                    self.show_status(server, user_ic)?;
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -289,11 +864,20 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
         user_ic: &mut xim::UserInputContext<KimeData>,
     ) -> Result<(), xim::ServerError> {
         log::info!(
-            "IC created style: {:?}, spot_location: {:?}",
+            "IC created style: {:?}, spot_location: {:?}, locale: {}",
             user_ic.ic.input_style(),
-            user_ic.ic.preedit_spot()
+            user_ic.ic.preedit_spot(),
+            user_ic.ic.locale()
         );
 
+        user_ic.user_data.encoding = ClientEncoding::from_locale(user_ic.ic.locale());
+
+        if let Some(status_area) = self.status_area.take() {
+            if status_area.input_context_id.is_none() {
+                user_ic.user_data.status_area = Some(status_area.area);
+            }
+        }
+
         server.set_event_mask(&user_ic.ic, EVENT_MASK, 0)?;
 
         Ok(())
@@ -314,13 +898,37 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
         user_ic: &mut xim::UserInputContext<Self::InputContextData>,
         xev: &KeyPressEvent,
     ) -> Result<bool, xim::ServerError> {
-        // skip release
-        if xev.response_type != KEY_PRESS_EVENT {
-            return Ok(false);
+        // Made by `flush_request` when hotkey is not completed in time
+        if xev.detail == FLUSH_KEYCODE {
+            if user_ic.user_data.engine.flush_held_keys() & InputResult_REPLAY != 0 {
+                self.replay_held_keys(server, user_ic)?;
+            }
+
+            self.update_held_ic(user_ic);
+
+            return Ok(true);
         }
 
+        // release is only used for `Tap` hotkeys and never consumed
+        let release = xev.response_type != KEY_PRESS_EVENT;
+
         log::trace!("{:?}", xev);
 
+        // Client doesn't know press of held key yet
+        let held = release
+            && match user_ic
+                .user_data
+                .held_keys
+                .iter_mut()
+                .find(|held| held.press.detail == xev.detail)
+            {
+                Some(held) => {
+                    held.release = Some(*xev);
+                    true
+                }
+                None => false,
+            };
+
         let mut state = 0;
 
         macro_rules! check_flag {
@@ -343,17 +951,73 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
 
         let numlock = check_flag!(MOD2);
 
+        if numlock {
+            state |= ModifierState_NUMLOCK;
+        }
+
+        if check_flag!(LOCK) {
+            state |= ModifierState_CAPSLOCK;
+        }
+
         if check_flag!(MOD4) {
             state |= ModifierState_SUPER;
         }
 
-        let ret =
-            user_ic
-                .user_data
-                .engine
-                .press_key(&self.config, xev.detail as u16, numlock, state);
+        if self.keyboard_mapping.is_none() {
+            self.keyboard_mapping = Some(KeyboardMapping::load(server.conn())?);
+        }
 
-        self.process_input_result(server, user_ic, ret)
+        if u16::from(xev.state) & self.keyboard_mapping.as_ref().unwrap().level3_mask() != 0 {
+            state |= ModifierState_ALTGR;
+        }
+
+        let ret = match self.config.key_matching() {
+            KeyMatching::Keysym => {
+                let keysym = self.keyboard_mapping.as_ref().unwrap().keysym(
+                    xev.detail,
+                    check_flag!(SHIFT),
+                    numlock,
+                );
+
+                let engine = &mut user_ic.user_data.engine;
+
+                if release {
+                    engine.release_keysym(&self.config, keysym, state)
+                } else {
+                    engine.press_keysym(&self.config, keysym, state)
+                }
+            }
+            KeyMatching::Keycode => {
+                let engine = &mut user_ic.user_data.engine;
+
+                if release {
+                    engine.release_key(&self.config, xev.detail as u16, state)
+                } else {
+                    engine.press_key(&self.config, xev.detail as u16, state)
+                }
+            }
+        };
+
+        if ret & InputResult_REPLAY != 0 {
+            self.replay_held_keys(server, user_ic)?;
+            self.update_held_ic(user_ic);
+            return Ok(self.handle_forward_event(server, user_ic, xev)? || held);
+        }
+
+        if !release {
+            if ret & InputResult_HOLD != 0 {
+                user_ic.user_data.held_keys.push(HeldKey {
+                    press: *xev,
+                    release: None,
+                });
+            } else {
+                user_ic.user_data.held_keys.clear();
+            }
+
+            self.update_held_ic(user_ic);
+        }
+
+        Ok(self.process_input_result(server, user_ic, ret)? || held)
     }
 
     fn handle_destroy_ic(
@@ -363,6 +1027,14 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
     ) -> Result<(), xim::ServerError> {
         log::info!("destroy_ic");
 
+        if self
+            .held_ic
+            .as_ref()
+            .map_or(false, |held| held.is_ic(self.request_window, &user_ic.ic))
+        {
+            self.held_ic = None;
+        }
+
         if let Some(pe) = user_ic.user_data.pe {
             self.preedit_windows
                 .remove(&pe)
@@ -370,6 +1042,13 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
                 .clean(server.conn())?;
         }
 
+        if let Some(status_window) = user_ic.user_data.status_window {
+            self.preedit_windows
+                .remove(&status_window)
+                .unwrap()
+                .clean(server.conn())?;
+        }
+
         Ok(())
     }
 
@@ -388,7 +1067,24 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
             }
         }
 
-        Ok(())
+        if user_ic.user_data.app.is_none() {
+            let window = user_ic
+                .ic
+                .app_win()
+                .map_or(user_ic.ic.client_win(), |w| w.get());
+            let app = find_wm_class(server.conn(), window)?;
+            log::debug!("Focused app: {}", app);
+            user_ic.user_data.app = Some(app);
+        }
+
+        // Result also shows preedit which is kept by `on_focus_out`
+        let ret = match user_ic.user_data.app.as_deref() {
+            Some(app) => user_ic.user_data.engine.set_app(app, &self.config),
+            None => user_ic.user_data.engine.focus_in(),
+        };
+        self.process_input_result(server, user_ic, ret)?;
+
+        self.show_status(server, user_ic)
     }
 
     fn handle_unset_focus(
@@ -396,10 +1092,19 @@ impl<C: HasConnection> ServerHandler<X11rbServer<C>> for KimeHandler {
         server: &mut X11rbServer<C>,
         user_ic: &mut xim::UserInputContext<Self::InputContextData>,
     ) -> Result<(), xim::ServerError> {
+        self.hide_status(server, user_ic)?;
+
+        user_ic.user_data.held_keys.clear();
+
+        // Don't touch engine when engine is not ready
         if user_ic.user_data.engine_ready {
-            self.reset(server, user_ic)
-        } else {
-            Ok(())
+            user_ic.user_data.engine.focus_out(&self.config);
+            self.update_held_ic(user_ic);
+            // Kept preedit is hidden until focus is back
+            self.commit(server, user_ic)?;
+            user_ic.user_data.engine.clear_commit();
         }
+
+        Ok(())
     }
 }
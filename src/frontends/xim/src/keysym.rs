@@ -0,0 +1,78 @@
+use x11rb::{connection::Connection, protocol::xproto::ConnectionExt as _};
+
+const XK_ISO_LEVEL3_SHIFT: u32 = 0xFE03;
+
+/// Keysyms of each keycode from `GetKeyboardMapping`
+pub struct KeyboardMapping {
+    min_keycode: u8,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<u32>,
+    /// Modifier mask which has ISO_Level3_Shift from `GetModifierMapping`, AltGr isn't always Mod5
+    level3_mask: u16,
+}
+
+impl KeyboardMapping {
+    pub fn load(conn: &impl Connection) -> Result<Self, xim::ServerError> {
+        let setup = conn.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let reply = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+        let mut mapping = Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode,
+            keysyms: reply.keysyms,
+            level3_mask: 0,
+        };
+
+        let modifiers = conn.get_modifier_mapping()?.reply()?;
+        let per_modifier = modifiers.keycodes_per_modifier().max(1) as usize;
+
+        mapping.level3_mask = modifiers
+            .keycodes
+            .chunks(per_modifier)
+            .enumerate()
+            .filter(|(_, keycodes)| {
+                keycodes
+                    .iter()
+                    .any(|&keycode| mapping.syms(keycode).contains(&XK_ISO_LEVEL3_SHIFT))
+            })
+            .fold(0, |mask, (index, _)| mask | 1 << index);
+
+        Ok(mapping)
+    }
+
+    /// All keysyms of keycode
+    fn syms(&self, keycode: u8) -> &[u32] {
+        let per_keycode = self.keysyms_per_keycode as usize;
+        let start = match keycode.checked_sub(self.min_keycode) {
+            Some(offset) => offset as usize * per_keycode,
+            None => return &[],
+        };
+
+        self.keysyms.get(start..start + per_keycode).unwrap_or(&[])
+    }
+
+    pub fn level3_mask(&self) -> u16 {
+        self.level3_mask
+    }
+
+    /// Keysym of first group, keypad keys are shifted by numlock
+    pub fn keysym(&self, keycode: u8, shift: bool, numlock: bool) -> u32 {
+        let syms = self.syms(keycode);
+
+        let normal = syms.first().copied().unwrap_or(0);
+        // NoSymbol in second column means same as first one
+        let shifted = syms
+            .get(1)
+            .copied()
+            .filter(|&sym| sym != 0)
+            .unwrap_or(normal);
+        let keypad = (0xFF80..=0xFFBD).contains(&shifted);
+
+        if shift ^ (numlock && keypad) {
+            shifted
+        } else {
+            normal
+        }
+    }
+}
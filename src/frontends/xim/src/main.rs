@@ -1,12 +1,30 @@
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
 use x11rb::{
     connection::Connection,
-    protocol::{ErrorKind, Event},
+    protocol::{xproto::ConnectionExt as _, ErrorKind, Event},
+    rust_connection::RustConnection,
 };
 use xim::{x11rb::HasConnection, XimConnections};
 
+mod ctext;
 mod handler;
+mod keysym;
 mod pe_window;
 
+/// Wait until connection is readable, `false` on timeout
+fn wait_readable(conn: &RustConnection, timeout: Duration) -> bool {
+    let mut fd = libc::pollfd {
+        fd: conn.stream().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+    // Interrupted poll is handled by next loop
+    unsafe { libc::poll(&mut fd, 1, timeout) != 0 }
+}
+
 fn main() {
     kime_version::cli_boilerplate!((),);
 
@@ -17,15 +35,56 @@ fn main() {
 
     let config = kime_engine_cffi::Config::load();
 
-    let (conn, screen_num) =
-        x11rb::rust_connection::RustConnection::connect(None).expect("Connect X");
+    let (conn, screen_num) = RustConnection::connect(None).expect("Connect X");
     let mut server = xim::x11rb::X11rbServer::init(conn, screen_num, "kime", xim::ALL_LOCALES)
         .expect("Init XIM server");
     let mut connections = XimConnections::new();
-    let mut handler = self::handler::KimeHandler::new(screen_num, config);
+    let scale = self::pe_window::dpi_scale(server.conn());
+    log::info!("Preedit scale: {}", scale);
+    let mut handler = self::handler::KimeHandler::new(screen_num, config, scale);
+    let xim_protocol = server
+        .conn()
+        .intern_atom(false, b"_XIM_PROTOCOL")
+        .expect("Intern atom")
+        .reply()
+        .expect("Intern atom")
+        .atom;
 
     loop {
-        let e = server.conn().wait_for_event().expect("Wait event");
+        let e = match handler.held_timeout() {
+            // Wait event until held keys should be flushed
+            Some(timeout) => match server.conn().poll_for_event().expect("Poll event") {
+                Some(e) => e,
+                None => {
+                    server.conn().flush().expect("Flush connection");
+
+                    if wait_readable(server.conn(), timeout) {
+                        continue;
+                    }
+
+                    match handler.flush_request(server.conn(), xim_protocol) {
+                        Ok(Some(msg)) => Event::ClientMessage(msg),
+                        Ok(None) => continue,
+                        Err(err) => {
+                            log::error!("Can't flush held keys: {}", err);
+                            continue;
+                        }
+                    }
+                }
+            },
+            None => server.conn().wait_for_event().expect("Wait event"),
+        };
+
+        if let Event::ClientMessage(msg) = &e {
+            if msg.type_ == xim_protocol {
+                handler.set_request_window(msg.window);
+                match self::handler::read_status_area(server.conn(), msg) {
+                    Ok(status_area) => handler.set_status_area(status_area),
+                    Err(err) => log::warn!("Can't read status area: {}", err),
+                }
+            }
+        }
+
         match server.filter_event(&e, &mut connections, &mut handler) {
             // event has filtered
             Ok(true) => {}
@@ -41,7 +100,7 @@ fn main() {
                 }
                 Event::UnmapNotify(..) => {}
                 Event::DestroyNotify(..) => {}
-                Event::MappingNotify(..) => {}
+                Event::MappingNotify(..) => handler.mapping_notify(),
                 Event::Error(x11rb::x11_utils::X11Error {
                     error_kind: ErrorKind::RenderPicture,
                     ..
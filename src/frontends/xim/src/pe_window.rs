@@ -1,44 +1,76 @@
 mod bgra;
+mod font;
 
 use bgra::Bgra;
-use std::{num::NonZeroU32, sync::Arc};
+pub use font::FontSet;
+use std::num::NonZeroU32;
 
 use image::ImageBuffer;
-use rusttype::Font;
 use x11rb::{
     connection::Connection,
-    protocol::xproto::{
-        AtomEnum, ConfigureNotifyEvent, ConnectionExt as _, CreateGCAux, CreateWindowAux,
-        EventMask, ExposeEvent, ImageFormat, PropMode, WindowClass, EXPOSE_EVENT,
+    protocol::{
+        randr::ConnectionExt as _,
+        xproto::{
+            AtomEnum, ConfigureNotifyEvent, ConfigureWindowAux, ConnectionExt as _, CreateGCAux,
+            CreateWindowAux, EventMask, ExposeEvent, ImageFormat, PropMode, Screen, WindowClass,
+            EXPOSE_EVENT,
+        },
     },
     wrapper::ConnectionExt as _,
 };
 
+/// Area of monitor in root coordinate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Monitor {
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+}
+
+impl Monitor {
+    fn contains(&self, (x, y): (i16, i16)) -> bool {
+        let (x, y) = (x as i32, y as i32);
+        let (left, top) = (self.x as i32, self.y as i32);
+
+        (left..left + self.width as i32).contains(&x)
+            && (top..top + self.height as i32).contains(&y)
+    }
+}
+
 pub struct PeWindow {
     preedit_window: NonZeroU32,
     preedit: String,
     gc: u32,
     text_pos: (u32, u32),
     text_scale: rusttype::Scale,
-    font: Arc<Font<'static>>,
     image_buffer: ImageBuffer<Bgra, Vec<u8>>,
+    /// Root coordinate of preedit spot
+    spot: (i16, i16),
+    /// Minimum size of window which fits one syllable
+    min_size: u16,
+    size: (u16, u16),
+    /// Monitor which contains spot
+    monitor: Monitor,
 }
 
 impl PeWindow {
     pub fn new(
         conn: &impl Connection,
-        (font, font_size): (Arc<Font<'static>>, f32),
+        font_size: f32,
         app_win: Option<NonZeroU32>,
         spot_location: xim::Point,
         screen_num: usize,
     ) -> Result<Self, xim::ServerError> {
-        let size = (font_size * 1.7) as u16;
-        let size = (size, size);
+        let min_size = (font_size * 1.7) as u16;
+        let size = (min_size, min_size);
         let gc = conn.generate_id()?;
         let preedit_window = conn.generate_id()?;
 
         let screen = &conn.setup().roots[screen_num];
-        let pos = find_position(conn, screen.root, app_win, spot_location)?;
+        let spot = find_position(conn, screen.root, app_win, spot_location)?;
+        let monitor = find_monitor(conn, screen, spot);
+        let pos = clamp_position(spot, size, monitor);
 
         conn.create_window(
             screen.root_depth,
@@ -100,10 +132,13 @@ impl PeWindow {
             preedit_window: NonZeroU32::new(preedit_window).unwrap(),
             preedit: String::with_capacity(10),
             gc,
-            font,
             text_pos: ((font_size * 0.36) as _, (font_size * 0.36) as _),
             text_scale: rusttype::Scale::uniform(font_size as f32),
             image_buffer: ImageBuffer::new(size.0 as _, size.1 as _),
+            spot,
+            min_size,
+            size,
+            monitor,
         })
     }
 
@@ -119,7 +154,11 @@ impl PeWindow {
         self.preedit_window
     }
 
-    fn redraw(&mut self, conn: &impl Connection) -> Result<(), xim::ServerError> {
+    fn redraw(
+        &mut self,
+        conn: &impl Connection,
+        fonts: &mut FontSet,
+    ) -> Result<(), xim::ServerError> {
         const BACKGROUND: Bgra = Bgra([255, 255, 255, 255]);
         const FOREGROUND: Bgra = Bgra([0, 0, 0, 255]);
 
@@ -128,13 +167,11 @@ impl PeWindow {
         let rect = imageproc::rect::Rect::at(0, 0)
             .of_size(self.image_buffer.width(), self.image_buffer.height());
         imageproc::drawing::draw_filled_rect_mut(&mut self.image_buffer, rect, BACKGROUND);
-        imageproc::drawing::draw_text_mut(
+        fonts.draw(
             &mut self.image_buffer,
             FOREGROUND,
-            self.text_pos.0 as i32,
-            self.text_pos.1 as i32,
+            (self.text_pos.0 as i32, self.text_pos.1 as i32),
             self.text_scale,
-            &self.font,
             &self.preedit,
         );
 
@@ -154,8 +191,12 @@ impl PeWindow {
         Ok(())
     }
 
-    pub fn expose(&mut self, conn: &impl Connection) -> Result<(), xim::ServerError> {
-        self.redraw(conn)?;
+    pub fn expose(
+        &mut self,
+        conn: &impl Connection,
+        fonts: &mut FontSet,
+    ) -> Result<(), xim::ServerError> {
+        self.redraw(conn, fonts)?;
         Ok(())
     }
 
@@ -163,9 +204,12 @@ impl PeWindow {
         &mut self,
         e: ConfigureNotifyEvent,
         conn: &impl Connection,
+        fonts: &mut FontSet,
     ) -> Result<(), xim::ServerError> {
-        self.image_buffer = ImageBuffer::new(e.width as _, e.height as _);
-        self.redraw(conn)?;
+        if (e.width as u32, e.height as u32) != self.image_buffer.dimensions() {
+            self.image_buffer = ImageBuffer::new(e.width as _, e.height as _);
+        }
+        self.redraw(conn, fonts)?;
         Ok(())
     }
 
@@ -190,12 +234,99 @@ impl PeWindow {
         Ok(())
     }
 
-    pub fn set_preedit(&mut self, s: &str) {
+    /// Set preedit text and resize window to fit it
+    pub fn set_preedit(
+        &mut self,
+        conn: &impl Connection,
+        fonts: &mut FontSet,
+        s: &str,
+    ) -> Result<(), xim::ServerError> {
         self.preedit.clear();
         self.preedit.push_str(s);
+
+        let text_width = fonts.text_width(&self.preedit, self.text_scale).ceil() as u32;
+        let width = (text_width + self.text_pos.0 * 2).max(self.min_size as u32);
+        let size = (width.min(self.monitor.width as u32) as u16, self.size.1);
+
+        if size != self.size {
+            self.size = size;
+            let pos = clamp_position(self.spot, size, self.monitor);
+
+            conn.configure_window(
+                self.preedit_window.get(),
+                &ConfigureWindowAux::new()
+                    .x(pos.0 as i32)
+                    .y(pos.1 as i32)
+                    .width(size.0 as u32)
+                    .height(size.1 as u32),
+            )?;
+        }
+
+        Ok(())
     }
 }
 
+/// Scale factor from `Xft.dpi` resource, 96 dpi is `1.0`
+pub fn dpi_scale(conn: &impl Connection) -> f32 {
+    const BASE_DPI: f32 = 96.0;
+
+    let dpi = match x11rb::resource_manager::new_from_resource_manager(conn) {
+        Ok(Some(db)) => db.get_value::<f32>("Xft.dpi", "").ok().flatten(),
+        _ => None,
+    };
+
+    dpi.filter(|dpi| *dpi > 0.0)
+        .map_or(1.0, |dpi| dpi / BASE_DPI)
+}
+
+/// Find RandR monitor which contains spot
+///
+/// Use whole screen when RandR is not supported
+fn find_monitor(conn: &impl Connection, screen: &Screen, spot: (i16, i16)) -> Monitor {
+    let screen_monitor = Monitor {
+        x: 0,
+        y: 0,
+        width: screen.width_in_pixels,
+        height: screen.height_in_pixels,
+    };
+
+    let monitors = match conn.randr_get_monitors(screen.root, true) {
+        Ok(cookie) => match cookie.reply() {
+            Ok(reply) => reply.monitors,
+            Err(err) => {
+                log::debug!("Can't get RandR monitors: {}", err);
+                return screen_monitor;
+            }
+        },
+        Err(err) => {
+            log::debug!("Can't get RandR monitors: {}", err);
+            return screen_monitor;
+        }
+    };
+
+    let to_monitor = |m: &x11rb::protocol::randr::MonitorInfo| Monitor {
+        x: m.x,
+        y: m.y,
+        width: m.width,
+        height: m.height,
+    };
+
+    monitors
+        .iter()
+        .map(to_monitor)
+        .find(|m| m.contains(spot))
+        .or_else(|| monitors.iter().find(|m| m.primary).map(to_monitor))
+        .unwrap_or(screen_monitor)
+}
+
+/// Keep window inside of monitor
+fn clamp_position(pos: (i16, i16), size: (u16, u16), monitor: Monitor) -> (i16, i16) {
+    let max_x = monitor.x + monitor.width.saturating_sub(size.0) as i16;
+    let max_y = monitor.y + monitor.height.saturating_sub(size.1) as i16;
+
+    (pos.0.clamp(monitor.x, max_x), pos.1.clamp(monitor.y, max_y))
+}
+
 fn find_position(
     conn: &impl Connection,
     root: u32,
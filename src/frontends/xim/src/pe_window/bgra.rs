@@ -1,6 +1,21 @@
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub struct Bgra(pub [u8; 4]);
 
+impl Bgra {
+    /// Blend `other` over `self` with coverage `alpha` in `0.0..=1.0`
+    pub fn blend_alpha(&mut self, other: Bgra, alpha: f32) {
+        for (a, b) in self.0[..3].iter_mut().zip(other.0[..3].iter()) {
+            *a = (*b as f32 * alpha + *a as f32 * (1.0 - alpha)) as u8;
+        }
+    }
+}
+
+impl From<image::Rgba<u8>> for Bgra {
+    fn from(image::Rgba([r, g, b, a]): image::Rgba<u8>) -> Self {
+        Self([b, g, r, a])
+    }
+}
+
 impl image::Pixel for Bgra {
     type Subpixel = u8;
 
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, rc::Rc};
+
+use image::{imageops::FilterType, ImageBuffer, RgbaImage};
+use rusttype::{point, Font, Scale};
+
+use kime_engine_cffi::Config;
+
+use super::bgra::Bgra;
+
+struct FallbackFont {
+    /// Borrowed from `FontSet::config` which outlives font
+    font: Font<'static>,
+    data: &'static [u8],
+    index: u32,
+    /// Font has color bitmap (CBDT/sbix)
+    color: bool,
+    /// Rasterized color bitmap of (char, height)
+    color_cache: HashMap<(char, u32), Option<RgbaImage>>,
+}
+
+impl FallbackFont {
+    /// # Safety
+    ///
+    /// `data` must be alive until font is dropped
+    unsafe fn new(data: &[u8], index: u32) -> Option<Self> {
+        let data: &'static [u8] = std::mem::transmute(data);
+        let color = ttf_parser::Face::parse(data, index)
+            .map(|face| face.tables().cbdt.is_some() || face.tables().sbix.is_some())
+            .unwrap_or(false);
+
+        Some(Self {
+            font: Font::try_from_bytes_and_index(data, index)?,
+            data,
+            index,
+            color,
+            color_cache: HashMap::new(),
+        })
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph(c).id().0 != 0
+    }
+
+    fn color_bitmap(&mut self, c: char, height: u32) -> Option<&RgbaImage> {
+        if !self.color {
+            return None;
+        }
+
+        if !self.color_cache.contains_key(&(c, height)) {
+            let bitmap = self.rasterize_color(c, height);
+            self.color_cache.insert((c, height), bitmap);
+        }
+
+        self.color_cache.get(&(c, height))?.as_ref()
+    }
+
+    fn rasterize_color(&self, c: char, height: u32) -> Option<RgbaImage> {
+        let face = ttf_parser::Face::parse(self.data, self.index).ok()?;
+        let glyph = face.glyph_index(c)?;
+        let raster = face.glyph_raster_image(glyph, height as u16)?;
+        let image = image::load_from_memory(raster.data).ok()?.to_rgba8();
+
+        if image.height() == 0 {
+            return None;
+        }
+
+        let width = image.width() * height / image.height();
+        Some(image::imageops::resize(
+            &image,
+            width,
+            height,
+            FilterType::Triangle,
+        ))
+    }
+}
+
+struct LaidGlyph {
+    font: usize,
+    c: char,
+    x: f32,
+    advance: f32,
+}
+
+/// Preedit font and fallback fonts which are loaded when glyph is missing
+pub struct FontSet {
+    /// Font data is owned by `config`, declared first to be dropped before it
+    fonts: Vec<FallbackFont>,
+    /// Index of font which is used for char
+    cache: HashMap<char, usize>,
+    config: Rc<Config>,
+}
+
+impl FontSet {
+    pub fn new(config: Rc<Config>) -> Self {
+        let (data, index, _size) = config.xim_font();
+        // SAFETY: `config` is kept in `FontSet`
+        let font = unsafe { FallbackFont::new(data, index) }.expect("Load xim preedit font");
+
+        Self {
+            fonts: vec![font],
+            cache: HashMap::new(),
+            config,
+        }
+    }
+
+    fn font_index(&mut self, c: char) -> usize {
+        if let Some(index) = self.cache.get(&c) {
+            return *index;
+        }
+
+        let index = self
+            .fonts
+            .iter()
+            .position(|font| font.has_glyph(c))
+            .or_else(|| self.load_fallback(c))
+            .unwrap_or(0);
+
+        self.cache.insert(c, index);
+        index
+    }
+
+    fn load_fallback(&mut self, c: char) -> Option<usize> {
+        if c.is_control() {
+            return None;
+        }
+
+        let (data, index) = self.config.fallback_font(c)?;
+        // SAFETY: `self.config` is dropped after `self.fonts`
+        let font = unsafe { FallbackFont::new(data, index)? };
+
+        log::debug!("Use fallback font for {:?}", c);
+
+        self.fonts.push(font);
+        Some(self.fonts.len() - 1)
+    }
+
+    fn layout(&mut self, text: &str, scale: Scale) -> Vec<LaidGlyph> {
+        let mut x = 0.0;
+
+        text.chars()
+            .map(|c| {
+                let font = self.font_index(c);
+                let advance = self.fonts[font]
+                    .font
+                    .glyph(c)
+                    .scaled(scale)
+                    .h_metrics()
+                    .advance_width;
+                let glyph = LaidGlyph {
+                    font,
+                    c,
+                    x,
+                    advance,
+                };
+                x += advance;
+                glyph
+            })
+            .collect()
+    }
+
+    pub fn text_width(&mut self, text: &str, scale: Scale) -> f32 {
+        self.layout(text, scale)
+            .last()
+            .map_or(0.0, |glyph| glyph.x + glyph.advance)
+    }
+
+    pub fn draw(
+        &mut self,
+        image: &mut ImageBuffer<Bgra, Vec<u8>>,
+        color: Bgra,
+        (x, y): (i32, i32),
+        scale: Scale,
+        text: &str,
+    ) {
+        let ascent = self.fonts[0].font.v_metrics(scale).ascent;
+        let (width, height) = image.dimensions();
+
+        let mut put = |px: i32, py: i32, pixel: Bgra, alpha: f32| {
+            if px >= 0 && py >= 0 && (px as u32) < width && (py as u32) < height {
+                image
+                    .get_pixel_mut(px as u32, py as u32)
+                    .blend_alpha(pixel, alpha);
+            }
+        };
+
+        for glyph in self.layout(text, scale) {
+            let font = &mut self.fonts[glyph.font];
+            let glyph_x = x + glyph.x as i32;
+
+            if let Some(bitmap) = font.color_bitmap(glyph.c, scale.y as u32) {
+                for (bx, by, pixel) in bitmap.enumerate_pixels() {
+                    put(
+                        glyph_x + bx as i32,
+                        y + by as i32,
+                        Bgra::from(*pixel),
+                        pixel.0[3] as f32 / 255.0,
+                    );
+                }
+                continue;
+            }
+
+            let positioned = font
+                .font
+                .glyph(glyph.c)
+                .scaled(scale)
+                .positioned(point(glyph_x as f32, y as f32 + ascent));
+
+            if let Some(bb) = positioned.pixel_bounding_box() {
+                positioned.draw(|gx, gy, v| {
+                    put(bb.min.x + gx as i32, bb.min.y + gy as i32, color, v);
+                });
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use kime_engine_core::{Key, KeyMap};
+use kime_engine_backend_hangul::is_ksx1001_locale;
+use kime_engine_core::{HangulData, KeyMap, Translation};
 
 use ansi_term::Color;
 use kime_engine_cffi::{
@@ -60,7 +61,7 @@ enum Check {
     GtkImModule,
     #[strum(message = "QT_IM_MODULE has kime")]
     QtImModule,
-    #[strum(message = "LANG has UTF-8")]
+    #[strum(message = "LANG has UTF-8 or EUC-KR")]
     Lang,
 }
 
@@ -133,31 +134,90 @@ impl Check {
                     Err(err) => return CondResult::Fail(format!("Can't parse config.yaml: {err}")),
                 };
 
-                match config.engine.translation_layer {
-                    Some(ref raw_path) => {
-                        let path = match dirs.find_config_file(raw_path) {
-                            Some(path) => path,
-                            _ => {
-                                return CondResult::Ignore(
-                                    "translation layer configuration does not exist. No translation layer will be used".into())
-                            }
-                        };
-                        println!("Loading translation layer config: {}", path.display());
+                let layers = config
+                    .engine
+                    .translation_layer
+                    .iter()
+                    .chain(config.engine.category_translation_layers.values());
+
+                for raw_path in layers {
+                    let path = match dirs.find_config_file(raw_path) {
+                        Some(path) => path,
+                        _ => {
+                            return CondResult::Ignore(format!(
+                                "translation layer {} does not exist. No translation layer will be used",
+                                raw_path.display()
+                            ))
+                        }
+                    };
+                    println!("Loading translation layer config: {}", path.display());
+
+                    let _translation_layer: KeyMap<Translation> = match serde_yaml::from_str(
+                        &std::fs::read_to_string(path.as_path())
+                            .expect("Read translation layer config"),
+                    ) {
+                        Ok(c) => c,
+                        Err(err) => {
+                            return CondResult::Fail(format!("Can't parse {path:#?}: {err}"))
+                        }
+                    };
+                }
 
-                        let _translation_layer: KeyMap<Key> = match serde_yaml::from_str(
-                            &std::fs::read_to_string(path.as_path())
-                                .expect("Read translation layer config"),
-                        ) {
-                            Ok(c) => c,
+                let latin_layout = &config.engine.latin.layout;
+                // Custom layouts have priority over builtin ones like engine
+                let custom_layout =
+                    dirs.list_config_files("latin_layouts")
+                        .into_iter()
+                        .find(|path| {
+                            path.file_stem().and_then(|stem| stem.to_str()) == Some(latin_layout)
+                        });
+
+                match custom_layout {
+                    Some(path) => {
+                        println!("Loading latin layout: {}", path.display());
+
+                        let content = match std::fs::read_to_string(path.as_path()) {
+                            Ok(content) => content,
                             Err(err) => {
-                                return CondResult::Fail(format!("Can't parse {path:#?}: {err}"))
+                                return CondResult::Fail(format!("Can't read {path:#?}: {err}"))
                             }
                         };
+
+                        if let Err(err) = kime_engine_backend_latin::load_layout(&content) {
+                            return CondResult::Fail(format!("Can't parse {path:#?}: {err}"));
+                        }
                     }
-                    None => return CondResult::Ok,
-                };
+                    None => {
+                        let is_builtin = kime_engine_backend_latin::BUILTIN_LAYOUTS
+                            .iter()
+                            .any(|(name, _)| name == latin_layout);
+
+                        if !is_builtin {
+                            return CondResult::Fail(format!(
+                                "Can't find latin layout {}",
+                                latin_layout
+                            ));
+                        }
+                    }
+                }
 
-                // TODO: check layout
+                let hangul_config = &config.engine.hangul;
+                let hangul_data = HangulData::from_config_with_dir(hangul_config, &dirs);
+                let loaded: Vec<_> = hangul_data.layout_names().collect();
+
+                println!("Hangul layouts: {}", loaded.join(", "));
+
+                if let Ok((_, layout)) = kime_engine_core::read_layout_state() {
+                    if !layout.is_empty() {
+                        println!("Active hangul layout: {}", layout);
+                    }
+                }
+
+                for name in hangul_config.layout_names() {
+                    if !loaded.contains(&name) {
+                        return CondResult::Fail(format!("Can't find hangul layout {}", name));
+                    }
+                }
 
                 CondResult::Ok
             }
@@ -178,10 +238,14 @@ impl Check {
             Check::Lang => check_var(
                 "LANG",
                 |v| {
-                    let v = v.to_ascii_lowercase();
-                    v.ends_with("utf-8") || v.ends_with("utf8")
+                    let lower = v.to_ascii_lowercase();
+                    let lower = lower.split('@').next().unwrap_or_default();
+                    lower.ends_with("utf-8")
+                        || lower.ends_with("utf8")
+                        // Legacy korean locales are supported by kime-xim
+                        || is_ksx1001_locale(v)
                 },
-                "set LANG encoding UTF-8",
+                "set LANG encoding UTF-8 or EUC-KR",
             ),
         }
     }
@@ -195,7 +259,7 @@ fn check_input(
     engine.set_input_category(InputCategory::Hangul);
 
     for (key, preedit, commit) in tests.iter().copied() {
-        let ret = engine.press_key(config, key, false, 0);
+        let ret = engine.press_key(config, key, 0);
 
         let preedit_ret;
         let commit_ret;
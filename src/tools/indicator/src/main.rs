@@ -1,6 +1,7 @@
 use anyhow::Result;
 use kime_engine_cffi::{IconColor, IndicatorConfig as Config};
 use ksni::menu::*;
+use ksni::ToolTip;
 use std::net::Shutdown;
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::Path;
@@ -18,6 +19,8 @@ enum InputCategory {
 struct KimeTray {
     icon_name: &'static str,
     color: IconColor,
+    /// Current hangul layout
+    layout: String,
 }
 
 impl ksni::Tray for KimeTray {
@@ -36,6 +39,14 @@ impl ksni::Tray for KimeTray {
     fn attention_icon_name(&self) -> String {
         self.icon_name.into()
     }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: self.id(),
+            description: self.layout.clone(),
+            ..Default::default()
+        }
+    }
     fn menu(&self) -> Vec<MenuItem<Self>> {
         vec![StandardItem {
             label: "Exit".into(),
@@ -63,24 +74,28 @@ impl KimeTray {
             // TODO: should consider `default_category` config?
             icon_name: icon_name(InputCategory::Latin, color),
             color,
+            layout: String::new(),
         }
     }
-    pub fn update_with_bytes(&mut self, bytes: &[u8; 1]) {
+
+    /// Message is category byte followed by utf8 name of hangul layout
+    pub fn update_with_bytes(&mut self, bytes: &[u8]) {
         let category = match bytes[0] {
             1 => InputCategory::Hangul,
             _ => InputCategory::Latin,
         };
 
-        self.update(category);
+        self.update(category, &String::from_utf8_lossy(&bytes[1..]));
     }
 
-    pub fn update(&mut self, category: InputCategory) {
-        log::debug!("Update: {:?}", category);
+    pub fn update(&mut self, category: InputCategory, layout: &str) {
+        log::debug!("Update: {:?} {}", category, layout);
         self.icon_name = icon_name(category, self.color);
+        self.layout = layout.into();
     }
 }
 
-const EXIT_MESSAGE: &[u8; 1] = b"Z";
+const EXIT_MESSAGE: &[u8] = b"Z";
 
 fn try_terminate_previous_server(file_path: &Path) -> Result<()> {
     let mut client = UnixStream::connect(file_path)?;
@@ -102,8 +117,8 @@ fn indicator_server(file_path: &Path, color: IconColor) -> Result<()> {
 
     let listener = UnixListener::bind(file_path)?;
 
-    let mut current_bytes = [0; 1];
-    let mut read_buf = [0; 1];
+    let mut current_bytes = vec![0];
+    let mut read_buf = Vec::new();
 
     loop {
         let mut client = listener.accept()?.0;
@@ -111,14 +126,16 @@ fn indicator_server(file_path: &Path, color: IconColor) -> Result<()> {
         client.set_write_timeout(Some(Duration::from_secs(2))).ok();
         client.write_all(&current_bytes).ok();
         client.shutdown(Shutdown::Write).ok();
-        match client.read_exact(&mut read_buf) {
-            Ok(_) => {
-                if &read_buf == EXIT_MESSAGE {
+        read_buf.clear();
+        match client.read_to_end(&mut read_buf) {
+            // Client which only reads state sends nothing
+            Ok(_) if !read_buf.is_empty() => {
+                if read_buf == EXIT_MESSAGE {
                     log::info!("Receive exit message");
                     return Ok(());
                 }
 
-                current_bytes = read_buf;
+                current_bytes.clone_from(&read_buf);
 
                 handle.update(|tray| {
                     tray.update_with_bytes(&current_bytes);